@@ -164,6 +164,12 @@ fn main() {
         build.file(format!("src/gme/{}", file));
     }
 
+    // Exposes Spc_Emu features that aren't part of the C API
+    if spc {
+        build.file("src/shim/spc_shim.cpp");
+        build.include("src/gme");
+    }
+
     // Use cc crate's define API so it maps to the correct compiler flags on each toolchain.
     for flag in defines {
         build.define(flag, None);
@@ -174,6 +180,11 @@ fn main() {
 
     build.compile("gme");
 
+    // cc emits rerun-if-env-changed, which turns off cargo's default of rerunning on any change
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed=src/gme");
+    println!("cargo:rerun-if-changed=src/shim");
+
     // Compile C files separately (for emu2413.c and panning.c used by VRC7) to avoid C++ name mangling
     if nsf || nsfe {
         let mut c_build = cc::Build::new();
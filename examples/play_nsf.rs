@@ -1,34 +1,20 @@
-use cpal::SampleFormat;
-use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
-use game_music_emu::GameMusicEmu;
+use game_music_emu::player::{Player, PlayerEvent};
 
 fn main() {
-    let err_fn = |err| eprintln!("an error occurred on the output audio stream: {}", err);
-    let host = cpal::default_host();
-    let device = host.default_output_device().unwrap();
-    let config = device.default_output_config().unwrap();
-    let sample_rate = config.sample_rate().0;
+    let mut player = Player::open("assets/test.nsf").unwrap();
+    player.play().unwrap();
 
-    let game_music_emu = GameMusicEmu::from_file("assets/test.nsf", sample_rate).unwrap();
-    game_music_emu.start_track(0).unwrap();
-
-    let play_f32 = move |output_buffer: &mut [f32], _: &cpal::OutputCallbackInfo| {
-        let len = output_buffer.len();
-        let mut emu_buffer = vec![0i16; len];
-        game_music_emu.play(len, &mut emu_buffer).unwrap();
-        for (sample, &emu_sample) in output_buffer.iter_mut().zip(&emu_buffer) {
-            *sample = emu_sample as f32 / i16::MAX as f32;
+    while let Some(event) = player.wait_event() {
+        match event {
+            PlayerEvent::TrackStarted(track) => println!("Playing track {}", track + 1),
+            PlayerEvent::TrackEnded(track) if track + 1 < player.track_count() => {
+                player.next_track().unwrap()
+            }
+            PlayerEvent::TrackEnded(_) => break,
+            PlayerEvent::Error(err) => eprintln!("Emulation error: {}", err),
+            PlayerEvent::StreamError(err) => {
+                eprintln!("an error occurred on the output audio stream: {}", err)
+            }
         }
-    };
-
-    let stream = match config.sample_format() {
-        SampleFormat::F32 => device.build_output_stream(&config.config(), play_f32, err_fn, None),
-        _ => panic!("only implemented for f32"),
-    }
-    .unwrap();
-    stream.play().unwrap();
-
-    loop {
-        std::thread::sleep(std::time::Duration::from_secs(1));
     }
 }
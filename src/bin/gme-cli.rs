@@ -0,0 +1,479 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use game_music_emu::export::{self, RenderOptions, WavFormat};
+use game_music_emu::player::{Player, PlayerEvent};
+use game_music_emu::scan::{self, ScanReport, TrackRecord};
+use game_music_emu::{EmuTrackInfo, GameMusicEmu, PlaybackLimit, SAMPLE_RATES, identify_file};
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+
+/// Inspect, play and export game music files
+#[derive(Parser)]
+#[command(name = "gme-cli", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Show the information of every track and the names of the voices
+    Info { file: PathBuf },
+    /// Play a track on the default audio device
+    Play {
+        file: PathBuf,
+        /// Track to play, starting from 1
+        #[arg(short, long, default_value_t = 1)]
+        track: usize,
+        /// Voice to mute, by number starting from 1 or by name. Can be repeated.
+        #[arg(short, long)]
+        mute: Vec<String>,
+        /// Playback speed, where 1.0 is normal
+        #[arg(long, default_value_t = 1.0)]
+        tempo: f64,
+    },
+    /// Render tracks to WAV files
+    Export {
+        file: PathBuf,
+        /// Track to export, starting from 1. Exports every track if not given.
+        #[arg(short, long)]
+        track: Option<usize>,
+        /// Directory to write the files to
+        #[arg(short, long, default_value = ".")]
+        out: PathBuf,
+        /// Write 32-bit float samples instead of 16-bit
+        #[arg(long)]
+        float: bool,
+        #[arg(long, default_value_t = 44100, value_parser = parse_sample_rate)]
+        sample_rate: u32,
+        /// Play the looping section this many times before fading out
+        #[arg(long, conflicts_with = "length")]
+        loops: Option<u32>,
+        /// Seconds to play before fading out, instead of the length from the file
+        #[arg(long, value_parser = parse_seconds)]
+        length: Option<Duration>,
+        /// Seconds the fade out lasts, instead of the length from the file
+        #[arg(long, value_parser = parse_seconds)]
+        fade: Option<Duration>,
+    },
+    /// Detect the type of files from their extension or contents
+    Identify {
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+    /// List the metadata of every track of every music file in a directory and its
+    /// subdirectories. Files that fail to load are reported without stopping the scan.
+    Scan {
+        directory: PathBuf,
+        #[arg(short, long, value_enum, default_value_t = ScanFormat::Json)]
+        format: ScanFormat,
+        /// File to write to instead of standard output
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+#[derive(Copy, Clone, ValueEnum)]
+enum ScanFormat {
+    /// An object with a `tracks` array and an `errors` array
+    Json,
+    /// One row per track, with errors written to standard error
+    Csv,
+}
+
+fn main() -> ExitCode {
+    let result = match Cli::parse().command {
+        Command::Info { file } => info(&file),
+        Command::Play {
+            file,
+            track,
+            mute,
+            tempo,
+        } => play(&file, track, &mute, tempo),
+        Command::Export {
+            file,
+            track,
+            out,
+            float,
+            sample_rate,
+            loops,
+            length,
+            fade,
+        } => {
+            let limit = match (loops, length) {
+                (Some(loops), _) => PlaybackLimit::Loops(loops),
+                (None, Some(length)) => PlaybackLimit::Fixed(length),
+                (None, None) => PlaybackLimit::Auto,
+            };
+            let format = if float {
+                WavFormat::Float32
+            } else {
+                WavFormat::Pcm16
+            };
+            export_wav(&file, track, &out, sample_rate, format, limit, fade)
+        }
+        Command::Identify { files } => Ok(identify(&files)),
+        Command::Scan {
+            directory,
+            format,
+            output,
+        } => scan(&directory, format, output.as_deref()),
+    };
+    match result {
+        Ok(code) => code,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+type CliResult = Result<ExitCode, Box<dyn Error>>;
+
+fn parse_seconds(value: &str) -> Result<Duration, String> {
+    Duration::try_from_secs_f64(value.parse::<f64>().map_err(|err| err.to_string())?)
+        .map_err(|err| err.to_string())
+}
+
+fn parse_sample_rate(value: &str) -> Result<u32, String> {
+    let rate = value.parse::<u32>().map_err(|err| err.to_string())?;
+    if !SAMPLE_RATES.contains(&rate) {
+        return Err(format!(
+            "{} is not in {}..={}",
+            rate,
+            SAMPLE_RATES.start(),
+            SAMPLE_RATES.end()
+        ));
+    }
+    Ok(rate)
+}
+
+/// Converts a track number starting from 1 to an index, checking that it exists
+fn track_index(emu: &GameMusicEmu, track: usize) -> Result<usize, String> {
+    if track == 0 || track > emu.track_count() {
+        return Err(format!(
+            "Track {} doesn't exist. There are {} tracks.",
+            track,
+            emu.track_count()
+        ));
+    }
+    Ok(track - 1)
+}
+
+fn print_length(label: &str, length: Option<Duration>) {
+    if let Some(length) = length {
+        println!("  {:<10} {:.1}s", label, length.as_secs_f64());
+    }
+}
+
+fn print_track(track: usize, info: &EmuTrackInfo) {
+    println!("Track {}: {}", track + 1, info);
+    let fields = [
+        ("System", &info.system),
+        ("Game", &info.game),
+        ("Song", &info.song),
+        ("Author", &info.author),
+        ("Copyright", &info.copyright),
+        ("Comment", &info.comment),
+        ("Dumper", &info.dumper),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            println!("  {:<10} {}", label, value);
+        }
+    }
+    print_length("Length", info.length);
+    print_length("Intro", info.intro_length);
+    print_length("Loop", info.loop_length);
+    print_length("Fade", info.fade_length);
+    print_length("Plays for", Some(info.play_length));
+}
+
+fn info(file: &Path) -> CliResult {
+    let emu = GameMusicEmu::from_file(file, 44100)?;
+    println!("Type: {}", emu.emu_type().to_extension());
+    println!("Tracks: {}", emu.track_count());
+    println!("Voices:");
+    for (index, name) in emu.voice_names().iter().enumerate() {
+        println!("  {}. {}", index + 1, name);
+    }
+    for track in 0..emu.track_count() {
+        println!();
+        print_track(track, &emu.track_info(track as u32)?);
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Finds a voice by its number starting from 1, or its name
+fn voice_index(emu: &GameMusicEmu, voice: &str) -> Result<u32, String> {
+    let index = match voice.parse::<u32>() {
+        Ok(number) => number.checked_sub(1),
+        Err(_) => emu.voice_index(voice),
+    };
+    index
+        .filter(|&index| index < emu.voice_count())
+        .ok_or_else(|| {
+            format!(
+                "Unknown voice {:?}. The voices are: {}",
+                voice,
+                emu.voice_names().join(", ")
+            )
+        })
+}
+
+fn play(file: &Path, track: usize, mute: &[String], tempo: f64) -> CliResult {
+    // Check the arguments before opening the audio device, which decides the sample rate
+    let emu = GameMusicEmu::from_file(file, 44100)?;
+    let index = track_index(&emu, track)?;
+    let muted = mute
+        .iter()
+        .map(|voice| voice_index(&emu, voice))
+        .collect::<Result<Vec<_>, _>>()?;
+    print_track(index, &emu.track_info(index as u32)?);
+
+    let mut player = Player::new(|sample_rate| {
+        let mut emu = GameMusicEmu::from_file(file, sample_rate)?;
+        for &voice in &muted {
+            emu.mute_voice(voice, true)?;
+        }
+        emu.set_tempo(tempo);
+        Ok(emu)
+    })?;
+    player.start_track(index)?;
+    player.play()?;
+
+    while let Some(event) = player.wait_event() {
+        match event {
+            PlayerEvent::TrackStarted(_) => {}
+            PlayerEvent::TrackEnded(_) => break,
+            PlayerEvent::Error(err) => return Err(err.into()),
+            PlayerEvent::StreamError(err) => eprintln!("Audio output error: {}", err),
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn export_wav(
+    file: &Path,
+    track: Option<usize>,
+    out: &Path,
+    sample_rate: u32,
+    format: WavFormat,
+    limit: PlaybackLimit,
+    fade_length: Option<Duration>,
+) -> CliResult {
+    let mut emu = GameMusicEmu::from_file(file, sample_rate)?;
+    std::fs::create_dir_all(out)?;
+    let mut options = RenderOptions::default()
+        .format(format)
+        .limit(limit)
+        .on_progress(|progress| {
+            eprint!(
+                "\rTrack {}: {:3.0}%",
+                progress.track + 1,
+                progress.fraction() * 100.0
+            );
+            if progress.fraction() >= 1.0 {
+                eprintln!();
+            }
+            let _ = std::io::stderr().flush();
+        });
+    options.fade_length = fade_length;
+    let paths = match track {
+        Some(track) => {
+            let index = track_index(&emu, track)?;
+            let info = emu.track_info(index as u32)?;
+            let path = out.join(export::track_file_name(index, &info));
+            export::render_to_wav(&mut emu, &path, index, &mut options)?;
+            vec![path]
+        }
+        None => export::render_all_to_wav(&mut emu, out, &mut options)?,
+    };
+    for path in paths {
+        println!("{}", path.display());
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+fn identify(files: &[PathBuf]) -> ExitCode {
+    let mut code = ExitCode::SUCCESS;
+    for file in files {
+        match identify_file(file) {
+            Ok(Some(emu_type)) => println!(
+                "{}: {} ({})",
+                file.display(),
+                emu_type.to_extension(),
+                emu_type.system().unwrap_or("unknown system")
+            ),
+            Ok(None) => println!("{}: unknown", file.display()),
+            Err(err) => {
+                eprintln!("{}: {}", file.display(), err);
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+    code
+}
+
+/// A [TrackRecord] flattened for output, with lengths in milliseconds and the track numbered
+/// from 1
+#[derive(serde::Serialize)]
+struct ScanRow<'a> {
+    path: String,
+    track: usize,
+    track_count: usize,
+    #[serde(rename = "type")]
+    emu_type: &'static str,
+    system: Option<&'a str>,
+    game: Option<&'a str>,
+    song: Option<&'a str>,
+    author: Option<&'a str>,
+    copyright: Option<&'a str>,
+    dumper: Option<&'a str>,
+    comment: Option<&'a str>,
+    length_ms: Option<u128>,
+    intro_length_ms: Option<u128>,
+    loop_length_ms: Option<u128>,
+    fade_length_ms: Option<u128>,
+    play_length_ms: u128,
+    voice_count: usize,
+    voice_names: &'a [String],
+}
+
+impl<'a> ScanRow<'a> {
+    const CSV_HEADER: [&'static str; 18] = [
+        "path",
+        "track",
+        "track_count",
+        "type",
+        "system",
+        "game",
+        "song",
+        "author",
+        "copyright",
+        "dumper",
+        "comment",
+        "length_ms",
+        "intro_length_ms",
+        "loop_length_ms",
+        "fade_length_ms",
+        "play_length_ms",
+        "voice_count",
+        "voice_names",
+    ];
+
+    fn new(record: &'a TrackRecord) -> Self {
+        let info = &record.info;
+        Self {
+            path: record.path.display().to_string(),
+            track: record.track + 1,
+            track_count: record.track_count,
+            emu_type: record.emu_type.to_extension(),
+            system: info.system.as_deref(),
+            game: info.game.as_deref(),
+            song: info.song.as_deref(),
+            author: info.author.as_deref(),
+            copyright: info.copyright.as_deref(),
+            dumper: info.dumper.as_deref(),
+            comment: info.comment.as_deref(),
+            length_ms: info.length.map(|length| length.as_millis()),
+            intro_length_ms: info.intro_length.map(|length| length.as_millis()),
+            loop_length_ms: info.loop_length.map(|length| length.as_millis()),
+            fade_length_ms: info.fade_length.map(|length| length.as_millis()),
+            play_length_ms: info.play_length.as_millis(),
+            voice_count: record.voice_names.len(),
+            voice_names: &record.voice_names,
+        }
+    }
+
+    /// Fields in the order of [Self::CSV_HEADER], with the voice names separated by `;`
+    fn csv_record(&self) -> [String; 18] {
+        let text = |value: Option<&str>| value.unwrap_or_default().to_string();
+        let number = |value: Option<u128>| value.map(|n| n.to_string()).unwrap_or_default();
+        [
+            self.path.clone(),
+            self.track.to_string(),
+            self.track_count.to_string(),
+            self.emu_type.to_string(),
+            text(self.system),
+            text(self.game),
+            text(self.song),
+            text(self.author),
+            text(self.copyright),
+            text(self.dumper),
+            text(self.comment),
+            number(self.length_ms),
+            number(self.intro_length_ms),
+            number(self.loop_length_ms),
+            number(self.fade_length_ms),
+            self.play_length_ms.to_string(),
+            self.voice_count.to_string(),
+            self.voice_names.join(";"),
+        ]
+    }
+}
+
+#[derive(serde::Serialize)]
+struct ScanErrorRow {
+    path: String,
+    error: String,
+}
+
+fn scan(directory: &Path, format: ScanFormat, output: Option<&Path>) -> CliResult {
+    let report = scan::scan_directory(directory);
+    let writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(io::BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout().lock()),
+    };
+    match format {
+        ScanFormat::Json => write_scan_json(&report, writer)?,
+        ScanFormat::Csv => {
+            write_scan_csv(&report, writer)?;
+            for error in &report.errors {
+                eprintln!("{}: {}", error.path.display(), error.error);
+            }
+        }
+    }
+    eprintln!(
+        "Found {} tracks. Errors: {}",
+        report.tracks.len(),
+        report.errors.len()
+    );
+    Ok(ExitCode::SUCCESS)
+}
+
+fn write_scan_json(report: &ScanReport, mut writer: impl Write) -> Result<(), Box<dyn Error>> {
+    #[derive(serde::Serialize)]
+    struct Output<'a> {
+        tracks: Vec<ScanRow<'a>>,
+        errors: Vec<ScanErrorRow>,
+    }
+    let output = Output {
+        tracks: report.tracks.iter().map(ScanRow::new).collect(),
+        errors: report
+            .errors
+            .iter()
+            .map(|error| ScanErrorRow {
+                path: error.path.display().to_string(),
+                error: error.error.to_string(),
+            })
+            .collect(),
+    };
+    serde_json::to_writer_pretty(&mut writer, &output)?;
+    writeln!(writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_scan_csv(report: &ScanReport, writer: impl Write) -> Result<(), Box<dyn Error>> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(ScanRow::CSV_HEADER)?;
+    for record in &report.tracks {
+        writer.write_record(ScanRow::new(record).csv_record())?;
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -1,11 +1,157 @@
-#[derive(Clone, Default, Debug)]
+use crate::error::{GmeError, GmeErrorKind, GmeResult};
+use std::ops::RangeInclusive;
+
+/// Frequency equalizer parameters
+#[derive(Clone, Default, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EmuEqualizer {
+    /// -50.0 = muffled, 0 = flat, +5.0 = extra-crisp
     pub treble: f64,
+    /// 1 = full bass, 90 = average, 16000 = almost no bass
     pub bass: f64,
+    /// Reserved by Game Music Emu for future parameters (`d2` to `d9`). Currently ignored when
+    /// set and read back as zero.
+    pub reserved: [f64; 8],
 }
 
 impl EmuEqualizer {
-    pub fn new(treble: f64, bass: f64) -> Self {
-        Self { treble, bass }
+    /// Range of [Self::treble] that Game Music Emu documents
+    pub const TREBLE_RANGE: RangeInclusive<f64> = -50.0..=5.0;
+    /// Range of [Self::bass] that Game Music Emu documents
+    pub const BASS_RANGE: RangeInclusive<f64> = 1.0..=16000.0;
+
+    pub const fn new(treble: f64, bass: f64) -> Self {
+        Self {
+            treble,
+            bass,
+            reserved: [0.0; 8],
+        }
+    }
+
+    /// Returns an error with [GmeErrorKind::InvalidArgument] if the treble or bass is outside of
+    /// its documented range
+    pub fn validate(&self) -> GmeResult<()> {
+        if !Self::TREBLE_RANGE.contains(&self.treble) {
+            return Err(GmeError::with_kind(
+                GmeErrorKind::InvalidArgument,
+                format!(
+                    "Treble {} is outside of {:?}",
+                    self.treble,
+                    Self::TREBLE_RANGE
+                ),
+            ));
+        }
+        if !Self::BASS_RANGE.contains(&self.bass) {
+            return Err(GmeError::with_kind(
+                GmeErrorKind::InvalidArgument,
+                format!("Bass {} is outside of {:?}", self.bass, Self::BASS_RANGE),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl From<EqualizerPreset> for EmuEqualizer {
+    fn from(preset: EqualizerPreset) -> Self {
+        preset.equalizer()
+    }
+}
+
+/// Equalizer settings that Game Music Emu uses for the sound of particular hardware
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum EqualizerPreset {
+    /// No treble cut and full bass
+    Flat,
+    /// NES, the default for NSF
+    Nes,
+    /// Famicom, which is more muffled than the NES
+    Famicom,
+    /// Game Boy's built-in speaker
+    GameBoySpeaker,
+    /// Game Boy with headphones
+    GameBoyHeadphones,
+    /// Sega Genesis, the default for VGM
+    Genesis,
+    /// TV speaker
+    Tv,
+}
+
+impl EqualizerPreset {
+    pub const ALL: [EqualizerPreset; 7] = [
+        EqualizerPreset::Flat,
+        EqualizerPreset::Nes,
+        EqualizerPreset::Famicom,
+        EqualizerPreset::GameBoySpeaker,
+        EqualizerPreset::GameBoyHeadphones,
+        EqualizerPreset::Genesis,
+        EqualizerPreset::Tv,
+    ];
+
+    pub fn equalizer(&self) -> EmuEqualizer {
+        match self {
+            EqualizerPreset::Flat => EmuEqualizer::new(0.0, 1.0),
+            EqualizerPreset::Nes => EmuEqualizer::new(-1.0, 80.0),
+            EqualizerPreset::Famicom => EmuEqualizer::new(-15.0, 80.0),
+            EqualizerPreset::GameBoySpeaker => EmuEqualizer::new(-47.0, 2000.0),
+            EqualizerPreset::GameBoyHeadphones => EmuEqualizer::new(0.0, 300.0),
+            EqualizerPreset::Genesis => EmuEqualizer::new(-14.0, 80.0),
+            EqualizerPreset::Tv => EmuEqualizer::new(-8.0, 180.0),
+        }
+    }
+
+    /// Name of the preset, such as `"game-boy-speaker"`
+    pub fn name(&self) -> &'static str {
+        match self {
+            EqualizerPreset::Flat => "flat",
+            EqualizerPreset::Nes => "nes",
+            EqualizerPreset::Famicom => "famicom",
+            EqualizerPreset::GameBoySpeaker => "game-boy-speaker",
+            EqualizerPreset::GameBoyHeadphones => "game-boy-headphones",
+            EqualizerPreset::Genesis => "genesis",
+            EqualizerPreset::Tv => "tv",
+        }
+    }
+
+    /// Get a preset from its [Self::name], ignoring case and treating spaces and underscores as
+    /// hyphens. Returns `None` if the name is not recognized.
+    pub fn from_name(name: &str) -> Option<EqualizerPreset> {
+        let name = name.trim().to_ascii_lowercase().replace([' ', '_'], "-");
+        Self::ALL.into_iter().find(|preset| preset.name() == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_presets_are_valid() {
+        for preset in EqualizerPreset::ALL {
+            preset.equalizer().validate().unwrap();
+            assert_eq!(EqualizerPreset::from_name(preset.name()), Some(preset));
+        }
+    }
+
+    #[test]
+    fn test_from_name() {
+        assert_eq!(
+            EqualizerPreset::from_name("Game Boy Speaker"),
+            Some(EqualizerPreset::GameBoySpeaker)
+        );
+        assert_eq!(
+            EqualizerPreset::from_name("NES"),
+            Some(EqualizerPreset::Nes)
+        );
+        assert_eq!(EqualizerPreset::from_name("loud"), None);
+    }
+
+    #[test]
+    fn test_validate() {
+        let err = EmuEqualizer::new(-60.0, 80.0).validate().unwrap_err();
+        assert_eq!(err.kind(), GmeErrorKind::InvalidArgument);
+        assert!(EmuEqualizer::new(0.0, 0.0).validate().is_err());
+        assert!(EmuEqualizer::new(f64::NAN, 80.0).validate().is_err());
     }
 }
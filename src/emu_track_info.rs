@@ -1,9 +1,23 @@
-#[derive(Clone, Default)]
+use std::fmt;
+use std::time::Duration;
+
+/// Information about a track. Lengths and text fields are `None` when the file doesn't provide
+/// them, so an unknown value is never confused with zero or an empty string.
+#[derive(Clone, Default, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EmuTrackInfo {
-    pub length: Option<u32>,
-    pub intro_length: Option<u32>,
-    pub loop_length: Option<u32>,
-    pub play_length: u32,
+    /// Total length, if the file specifies one
+    pub length: Option<Duration>,
+    /// Length of the part before the looping section. Can be zero.
+    pub intro_length: Option<Duration>,
+    /// Length of one pass through the looping section. `None` if the track doesn't loop or this
+    /// is unknown.
+    pub loop_length: Option<Duration>,
+    /// Suggested fade-out length, if the file specifies one. Zero means no fade.
+    pub fade_length: Option<Duration>,
+    /// How long Game Music Emu plays the track: its length if known, otherwise the intro plus two
+    /// loops, otherwise 2.5 minutes
+    pub play_length: Duration,
     pub system: Option<String>,
     pub game: Option<String>,
     pub song: Option<String>,
@@ -17,4 +31,93 @@ impl EmuTrackInfo {
     pub fn new() -> Self {
         Self::default()
     }
+
+    /// How long playback lasts when the looping section is played `loops` times, followed by a
+    /// fade of length `fade`. Tracks without loop information use their length, or
+    /// [Self::play_length] if that is unknown too.
+    pub fn effective_length(&self, loops: u32, fade: Duration) -> Duration {
+        self.fade_start(loops) + fade
+    }
+
+    /// When to start fading out so the looping section plays `loops` times
+    pub(crate) fn fade_start(&self, loops: u32) -> Duration {
+        match self.loop_length {
+            Some(loop_length) => self.intro_length.unwrap_or_default() + loop_length * loops,
+            None => self.length.unwrap_or(self.play_length),
+        }
+    }
+}
+
+/// Shows the song, author and game that are known, followed by the length as `mm:ss`.
+/// For example: `Theme - Composer - Game (02:30)`.
+impl fmt::Display for EmuTrackInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [&self.song, &self.author, &self.game]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        if names.is_empty() {
+            f.write_str("Unknown track")?;
+        } else {
+            f.write_str(&names.join(" - "))?;
+        }
+        let length = self.length.unwrap_or(self.play_length);
+        write!(f, " ({})", format_mm_ss(length))
+    }
+}
+
+/// Formats a duration as minutes and seconds, like `03:07`
+pub(crate) fn format_mm_ss(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!("{:02}:{:02}", seconds / 60, seconds % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_effective_length() {
+        let looping = EmuTrackInfo {
+            intro_length: Some(Duration::from_secs(5)),
+            loop_length: Some(Duration::from_secs(20)),
+            play_length: Duration::from_secs(45),
+            ..EmuTrackInfo::default()
+        };
+        let fade = Duration::from_secs(8);
+        assert_eq!(looping.effective_length(2, fade), Duration::from_secs(53));
+
+        let fixed = EmuTrackInfo {
+            length: Some(Duration::from_secs(90)),
+            play_length: Duration::from_secs(90),
+            ..EmuTrackInfo::default()
+        };
+        assert_eq!(fixed.effective_length(2, fade), Duration::from_secs(98));
+
+        let unknown = EmuTrackInfo {
+            play_length: Duration::from_secs(150),
+            ..EmuTrackInfo::default()
+        };
+        assert_eq!(
+            unknown.effective_length(2, Duration::ZERO),
+            Duration::from_secs(150)
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        let info = EmuTrackInfo {
+            song: Some("Theme".into()),
+            game: Some("Game".into()),
+            length: Some(Duration::from_millis(187_500)),
+            ..EmuTrackInfo::default()
+        };
+        assert_eq!(info.to_string(), "Theme - Game (03:07)");
+        let info = EmuTrackInfo {
+            play_length: Duration::from_secs(150),
+            ..EmuTrackInfo::default()
+        };
+        assert_eq!(info.to_string(), "Unknown track (02:30)");
+    }
 }
@@ -1,3 +1,5 @@
+use crate::native;
+
 /// All supported emulator types
 #[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
 pub enum EmuType {
@@ -31,9 +33,10 @@ mod extensions {
 }
 
 impl EmuType {
-    /// Get an `EmuType` from a file extension
-    pub fn from_extension(value: &str) -> EmuType {
-        match value {
+    /// Get an `EmuType` from a file extension, ignoring case. Returns `None` if the extension is
+    /// not recognized.
+    pub fn from_extension(value: &str) -> Option<EmuType> {
+        Some(match value.to_ascii_uppercase().as_str() {
             extensions::AY => EmuType::Ay,
             extensions::GBS => EmuType::Gbs,
             extensions::GYM => EmuType::Gym,
@@ -45,8 +48,8 @@ impl EmuType {
             extensions::SPC => EmuType::Spc,
             extensions::VGM => EmuType::Vgm,
             extensions::VGZ => EmuType::Vgz,
-            _ => panic!(),
-        }
+            _ => return None,
+        })
     }
 
     /// Get a file extension from an `EmuType`
@@ -65,4 +68,83 @@ impl EmuType {
             EmuType::Vgz => extensions::VGZ,
         }
     }
+
+    /// Name of the system this type of music is generally for, e.g. "Nintendo NES". Returns
+    /// `None` if the emulator for this type was not compiled in.
+    pub fn system(&self) -> Option<&'static str> {
+        native::type_system(*self)
+    }
+
+    /// True if files of this type can contain more than one track. Returns `None` if the
+    /// emulator for this type was not compiled in.
+    pub fn is_multitrack(&self) -> Option<bool> {
+        native::type_multitrack(*self)
+    }
+
+    /// Number of tracks every file of this type has. Returns `None` if it varies by file or the
+    /// emulator for this type was not compiled in.
+    pub fn fixed_track_count(&self) -> Option<usize> {
+        native::fixed_track_count(*self).filter(|&count| count > 0)
+    }
+}
+
+/// Serialized as its file extension, such as `"NSF"`
+#[cfg(feature = "serde")]
+impl serde::Serialize for EmuType {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.to_extension())
+    }
+}
+
+/// Deserialized from a file extension, ignoring case
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for EmuType {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let extension = String::deserialize(deserializer)?;
+        EmuType::from_extension(&extension).ok_or_else(|| {
+            serde::de::Error::custom(format!("unknown emulator type extension: {}", extension))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        assert_eq!(serde_json::to_string(&EmuType::Nsfe).unwrap(), r#""NSFE""#);
+        let emu_type: EmuType = serde_json::from_str(r#""spc""#).unwrap();
+        assert_eq!(emu_type, EmuType::Spc);
+        assert!(serde_json::from_str::<EmuType>(r#""MP3""#).is_err());
+    }
+
+    #[test]
+    fn test_from_extension() {
+        assert_eq!(EmuType::from_extension("NSF"), Some(EmuType::Nsf));
+        assert_eq!(EmuType::from_extension("nsf"), Some(EmuType::Nsf));
+        assert_eq!(EmuType::from_extension("Vgz"), Some(EmuType::Vgz));
+        assert_eq!(EmuType::from_extension(""), None);
+        assert_eq!(EmuType::from_extension("MP3"), None);
+    }
+
+    #[test]
+    fn test_metadata() {
+        assert_eq!(EmuType::Nsf.system(), Some("Nintendo NES"));
+        assert_eq!(EmuType::Nsf.is_multitrack(), Some(true));
+        assert_eq!(EmuType::Nsf.fixed_track_count(), None);
+        assert_eq!(EmuType::Spc.is_multitrack(), Some(false));
+        assert_eq!(EmuType::Spc.fixed_track_count(), Some(1));
+    }
+
+    #[test]
+    fn test_extension_round_trip() {
+        for emu_type in [EmuType::Ay, EmuType::Nsfe, EmuType::Spc, EmuType::Vgm] {
+            assert_eq!(
+                EmuType::from_extension(emu_type.to_extension()),
+                Some(emu_type)
+            );
+        }
+    }
 }
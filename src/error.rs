@@ -1,15 +1,112 @@
-/// Contains an error message passed by Game Music Emu
+/// An error reported by Game Music Emu, or by this crate while talking to it. The original
+/// message is kept, and the [GmeErrorKind] lets callers branch on what went wrong without
+/// comparing strings.
 #[derive(Debug, thiserror::Error)]
-#[error("{0}")]
-pub struct GmeError(String);
+#[error("{message}")]
+pub struct GmeError {
+    kind: GmeErrorKind,
+    message: String,
+}
+
+/// The kind of a [GmeError]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum GmeErrorKind {
+    /// The data is not in a format the emulator recognizes
+    WrongFileType,
+    /// The data ended before everything the emulator needed was read
+    FileTooShort,
+    /// Game Music Emu could not allocate memory
+    OutOfMemory,
+    /// The emulator was not compiled in, or doesn't support something the file or call needs
+    Unsupported,
+    /// The data is recognized but malformed
+    CorruptData,
+    /// A track index is out of range
+    InvalidTrack,
+    /// Emulation failed, for example because of an illegal instruction
+    Emulation,
+    /// A file could not be opened, read or seeked
+    Io,
+    /// An argument passed to this crate is invalid, such as a path that isn't valid UTF-8
+    InvalidArgument,
+    /// A sample buffer's length is not a whole number of stereo frames
+    InvalidBufferLength,
+    /// Any error that doesn't fit the other kinds
+    Other,
+}
+
+impl GmeErrorKind {
+    /// Classify one of the error strings Game Music Emu returns
+    pub fn from_message(message: &str) -> Self {
+        let lowercase = message.to_ascii_lowercase();
+        match message {
+            "Wrong file type for this emulator" | "Not an SPC file" | "Not an m3u playlist" => {
+                Self::WrongFileType
+            }
+            "Unexpected end of file"
+            | "File data missing"
+            | "Missing file data"
+            | "Missing track data"
+            | "Data header missing"
+            | "ROM data missing"
+            | "Bank data missing" => Self::FileTooShort,
+            "Out of memory" => Self::OutOfMemory,
+            "Use full emulator for playback" | "File type must have a fixed track count of 1" => {
+                Self::Unsupported
+            }
+            "Invalid track" | "Invalid track in m3u playlist" => Self::InvalidTrack,
+            "Couldn't open file"
+            | "Couldn't read from file"
+            | "Couldn't read from GZ file"
+            | "Error seeking in file"
+            | "Error seeking in GZ file"
+            | "Read error"
+            | "NULL FILE pointer" => Self::Io,
+            _ if lowercase.contains("emulation error") => Self::Emulation,
+            _ if lowercase.contains("not supported")
+                || lowercase.contains("isn't supported")
+                || lowercase.contains("unsupported")
+                || lowercase.contains("not compiled in") =>
+            {
+                Self::Unsupported
+            }
+            _ if message.starts_with("Corrupt")
+                || message.starts_with("Invalid")
+                || message.starts_with("Unknown")
+                || message.starts_with("Bad ")
+                || message.starts_with("Excessive")
+                || message.starts_with("Extra file data")
+                || message.starts_with("Stream lacked")
+                || message.starts_with("Problem in m3u") =>
+            {
+                Self::CorruptData
+            }
+            _ => Self::Other,
+        }
+    }
+}
 
 impl GmeError {
+    /// Create an error from a Game Music Emu message. Its kind is classified from the message.
     pub fn new(message: String) -> Self {
-        Self(message)
+        Self::with_kind(GmeErrorKind::from_message(&message), message)
+    }
+
+    pub(crate) fn with_kind(kind: GmeErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
     }
 
+    pub fn kind(&self) -> GmeErrorKind {
+        self.kind
+    }
+
+    /// The original error message
     pub fn message(&self) -> &str {
-        &self.0
+        &self.message
     }
 }
 
@@ -37,3 +134,47 @@ impl From<GmeError> for GmeOrIoError {
 }
 
 // pub(crate) type GmeOrIoResult<T> = Result<T, GmeOrIoError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_message() {
+        let cases = [
+            (
+                "Wrong file type for this emulator",
+                GmeErrorKind::WrongFileType,
+            ),
+            ("Unexpected end of file", GmeErrorKind::FileTooShort),
+            ("Out of memory", GmeErrorKind::OutOfMemory),
+            (
+                "Uses unsupported audio expansion hardware",
+                GmeErrorKind::Unsupported,
+            ),
+            ("YM2413 FM sound isn't supported", GmeErrorKind::Unsupported),
+            ("Packed GYM file not supported", GmeErrorKind::Unsupported),
+            ("Corrupt SPC file", GmeErrorKind::CorruptData),
+            ("Invalid play address", GmeErrorKind::CorruptData),
+            ("Invalid track", GmeErrorKind::InvalidTrack),
+            (
+                "Emulation error (illegal instruction)",
+                GmeErrorKind::Emulation,
+            ),
+            ("SPC emulation error", GmeErrorKind::Emulation),
+            ("Couldn't open file", GmeErrorKind::Io),
+            ("Something new", GmeErrorKind::Other),
+        ];
+        for (message, kind) in cases {
+            assert_eq!(GmeErrorKind::from_message(message), kind, "{message}");
+        }
+    }
+
+    #[test]
+    fn test_message_is_preserved() {
+        let err = GmeError::new("Corrupt file".into());
+        assert_eq!(err.kind(), GmeErrorKind::CorruptData);
+        assert_eq!(err.message(), "Corrupt file");
+        assert_eq!(err.to_string(), "Corrupt file");
+    }
+}
@@ -0,0 +1,429 @@
+//! Rendering tracks to WAV files
+
+use crate::emu_track_info::EmuTrackInfo;
+use crate::error::{GmeError, GmeErrorKind, GmeOrIoError};
+use crate::playback_limit::PlaybackLimit;
+use crate::wrapper::{DEFAULT_FADE_LENGTH, GameMusicEmu};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Frames rendered between progress reports
+const CHUNK_FRAMES: usize = 4096;
+
+/// Sample format of an exported WAV file
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+pub enum WavFormat {
+    /// 16-bit integer PCM, exactly what the emulator produces
+    #[default]
+    Pcm16,
+    /// 32-bit float PCM, with [GameMusicEmu::set_gain] and [GameMusicEmu::set_soft_clip] applied
+    Float32,
+}
+
+/// How far a render has come, passed to [RenderOptions::on_progress]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct RenderProgress {
+    pub track: usize,
+    pub rendered: Duration,
+    /// Expected length of the whole render. If the track ends sooner because it goes silent,
+    /// the last report has this set to the actual length.
+    pub total: Duration,
+}
+
+impl RenderProgress {
+    /// Portion rendered, from 0.0 to 1.0
+    pub fn fraction(&self) -> f64 {
+        if self.total.is_zero() {
+            1.0
+        } else {
+            (self.rendered.as_secs_f64() / self.total.as_secs_f64()).min(1.0)
+        }
+    }
+}
+
+/// Settings for [render_to_wav], built from [RenderOptions::default]
+///
+/// ```
+/// use game_music_emu::PlaybackLimit;
+/// use game_music_emu::export::{RenderOptions, WavFormat};
+/// use std::time::Duration;
+///
+/// let options = RenderOptions::default()
+///     .format(WavFormat::Float32)
+///     .limit(PlaybackLimit::Loops(2))
+///     .fade_length(Duration::from_secs(5))
+///     .on_progress(|progress| println!("{:.0}%", progress.fraction() * 100.0));
+/// ```
+#[non_exhaustive]
+pub struct RenderOptions<'a> {
+    pub format: WavFormat,
+    /// Where the fade out starts. [PlaybackLimit::Auto] uses the track's length from the file,
+    /// or its play length if there is none. [PlaybackLimit::Infinite] can't be rendered.
+    pub limit: PlaybackLimit,
+    /// Length of the fade out. `None` uses the track's own fade length, or
+    /// [DEFAULT_FADE_LENGTH] if the file doesn't have one.
+    pub fade_length: Option<Duration>,
+    progress: Option<Box<dyn FnMut(RenderProgress) + 'a>>,
+}
+
+impl Default for RenderOptions<'_> {
+    fn default() -> Self {
+        Self {
+            format: WavFormat::default(),
+            limit: PlaybackLimit::Auto,
+            fade_length: None,
+            progress: None,
+        }
+    }
+}
+
+impl<'a> RenderOptions<'a> {
+    pub fn format(mut self, format: WavFormat) -> Self {
+        self.format = format;
+        self
+    }
+
+    pub fn limit(mut self, limit: PlaybackLimit) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Use `length` instead of the track's own fade length
+    pub fn fade_length(mut self, length: Duration) -> Self {
+        self.fade_length = Some(length);
+        self
+    }
+
+    /// Call `progress` after every block of rendered audio
+    pub fn on_progress(mut self, progress: impl FnMut(RenderProgress) + 'a) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+
+    /// When the fade out starts and how long it lasts for a track with `info`
+    fn fade(&self, info: &EmuTrackInfo) -> Result<(Duration, Duration), GmeError> {
+        let start = match self.limit {
+            PlaybackLimit::Auto => info.length.unwrap_or(info.play_length),
+            PlaybackLimit::Infinite => {
+                return Err(GmeError::with_kind(
+                    GmeErrorKind::InvalidArgument,
+                    "An infinite track can't be rendered",
+                ));
+            }
+            limit => limit.fade_start(info).unwrap_or(info.play_length),
+        };
+        let length = self
+            .fade_length
+            .or(info.fade_length)
+            .unwrap_or(DEFAULT_FADE_LENGTH);
+        Ok((start, length))
+    }
+}
+
+impl fmt::Debug for RenderOptions<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderOptions")
+            .field("format", &self.format)
+            .field("limit", &self.limit)
+            .field("fade_length", &self.fade_length)
+            .field("progress", &self.progress.is_some())
+            .finish()
+    }
+}
+
+/// Render `track` of `emu` to a WAV file at `path`, up to the end of its fade out. Afterwards,
+/// `emu` is left at the end of `track`, with its own playback limit and fade length applied
+/// again. Start a track before playing it.
+///
+/// Fails with [GmeErrorKind::Unsupported] if `emu` was created with
+/// [GameMusicEmu::new_multi_channel].
+pub fn render_to_wav(
+    emu: &mut GameMusicEmu,
+    path: impl AsRef<Path>,
+    track: usize,
+    options: &mut RenderOptions,
+) -> Result<(), GmeOrIoError> {
+    if emu.is_multi_channel() {
+        return Err(GmeError::with_kind(
+            GmeErrorKind::Unsupported,
+            "Multi-channel emulators can't be rendered to a stereo WAV file",
+        )
+        .into());
+    }
+    let info = emu.track_info(track as u32)?;
+    let (fade_start, fade_length) = options.fade(&info)?;
+    let file = BufWriter::new(File::create(path)?);
+
+    let limit = emu.playback_limit();
+    let playback_fade_length = emu.playback_fade_length();
+    emu.set_playback_limit(PlaybackLimit::Fixed(fade_start));
+    emu.set_playback_fade_length(fade_length);
+    let result = render(emu, file, track, fade_start + fade_length, options);
+    emu.set_playback_limit(limit);
+    emu.set_playback_fade_length(playback_fade_length);
+    result
+}
+
+/// Render every track of `emu` into `directory`, named after their number and song, such as
+/// `01 - Title.wav`. Returns the paths of the files.
+pub fn render_all_to_wav(
+    emu: &mut GameMusicEmu,
+    directory: impl AsRef<Path>,
+    options: &mut RenderOptions,
+) -> Result<Vec<PathBuf>, GmeOrIoError> {
+    let mut paths = Vec::new();
+    for track in 0..emu.track_count() {
+        let info = emu.track_info(track as u32)?;
+        let path = directory.as_ref().join(track_file_name(track, &info));
+        render_to_wav(emu, &path, track, options)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// File name [render_all_to_wav] uses for `track`, without characters that file systems reject
+pub fn track_file_name(track: usize, info: &EmuTrackInfo) -> String {
+    let name = match &info.song {
+        Some(song) => format!("{:02} - {}", track + 1, song),
+        None => format!("{:02}", track + 1),
+    };
+    let name: String = name
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+    format!("{}.wav", name.trim_end_matches(['.', ' ']))
+}
+
+fn render(
+    emu: &mut GameMusicEmu,
+    file: impl Write + Seek,
+    track: usize,
+    total: Duration,
+    options: &mut RenderOptions,
+) -> Result<(), GmeOrIoError> {
+    let sample_rate = emu.sample_rate();
+    let total_frames = (total.as_secs_f64() * sample_rate as f64).ceil() as usize;
+    let mut writer = WavWriter::new(file, options.format, sample_rate)?;
+    let mut samples = vec![0_i16; CHUNK_FRAMES * 2];
+    let mut float_samples = vec![0_f32; CHUNK_FRAMES * 2];
+
+    emu.start_track(track)?;
+    let mut frames = 0;
+    let mut report = |frames: usize, total: Duration| {
+        if let Some(progress) = &mut options.progress {
+            progress(RenderProgress {
+                track,
+                rendered: Duration::from_secs_f64(frames as f64 / sample_rate as f64),
+                total,
+            });
+        }
+    };
+    while frames < total_frames && !emu.track_ended() {
+        let len = (total_frames - frames).min(CHUNK_FRAMES) * 2;
+        match options.format {
+            WavFormat::Pcm16 => {
+                emu.play(&mut samples[..len])?;
+                writer.write_i16(&samples[..len])?;
+            }
+            WavFormat::Float32 => {
+                emu.play_f32(&mut float_samples[..len])?;
+                writer.write_f32(&float_samples[..len])?;
+            }
+        }
+        frames += len / 2;
+        report(frames, total);
+    }
+    if frames < total_frames {
+        report(
+            frames,
+            Duration::from_secs_f64(frames as f64 / sample_rate as f64),
+        );
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+/// Writes a stereo WAV file. The sizes in the header are filled in by [WavWriter::finish].
+struct WavWriter<W: Write + Seek> {
+    writer: W,
+    format: WavFormat,
+    data_len: u64,
+}
+
+impl<W: Write + Seek> WavWriter<W> {
+    fn new(mut writer: W, format: WavFormat, sample_rate: u32) -> io::Result<Self> {
+        const CHANNELS: u16 = 2;
+        let (format_tag, bits): (u16, u16) = match format {
+            WavFormat::Pcm16 => (1, 16),
+            WavFormat::Float32 => (3, 32),
+        };
+        let block_align = CHANNELS * bits / 8;
+
+        writer.write_all(b"RIFF")?;
+        writer.write_all(&0_u32.to_le_bytes())?;
+        writer.write_all(b"WAVE")?;
+        writer.write_all(b"fmt ")?;
+        // Formats other than integer PCM need the extension size, even when it's empty
+        let fmt_len: u32 = if format == WavFormat::Pcm16 { 16 } else { 18 };
+        writer.write_all(&fmt_len.to_le_bytes())?;
+        writer.write_all(&format_tag.to_le_bytes())?;
+        writer.write_all(&CHANNELS.to_le_bytes())?;
+        writer.write_all(&sample_rate.to_le_bytes())?;
+        writer.write_all(&(sample_rate * block_align as u32).to_le_bytes())?;
+        writer.write_all(&block_align.to_le_bytes())?;
+        writer.write_all(&bits.to_le_bytes())?;
+        if format != WavFormat::Pcm16 {
+            writer.write_all(&0_u16.to_le_bytes())?;
+            // Frame count, filled in by finish
+            writer.write_all(b"fact")?;
+            writer.write_all(&4_u32.to_le_bytes())?;
+            writer.write_all(&0_u32.to_le_bytes())?;
+        }
+        writer.write_all(b"data")?;
+        writer.write_all(&0_u32.to_le_bytes())?;
+        Ok(Self {
+            writer,
+            format,
+            data_len: 0,
+        })
+    }
+
+    fn write_i16(&mut self, samples: &[i16]) -> io::Result<()> {
+        for sample in samples {
+            self.writer.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_len += samples.len() as u64 * 2;
+        Ok(())
+    }
+
+    fn write_f32(&mut self, samples: &[f32]) -> io::Result<()> {
+        for sample in samples {
+            self.writer.write_all(&sample.to_le_bytes())?;
+        }
+        self.data_len += samples.len() as u64 * 4;
+        Ok(())
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        let data_len = u32::try_from(self.data_len)
+            .map_err(|_| io::Error::other("WAV files can't be larger than 4 GiB"))?;
+        let header_len = self.writer.stream_position()? as u32 - data_len;
+        self.writer.seek(SeekFrom::Start(4))?;
+        self.writer
+            .write_all(&(header_len - 8 + data_len).to_le_bytes())?;
+        if self.format != WavFormat::Pcm16 {
+            let frames = data_len / 8;
+            self.writer.seek(SeekFrom::Start(46))?;
+            self.writer.write_all(&frames.to_le_bytes())?;
+        }
+        self.writer.seek(SeekFrom::Start(header_len as u64 - 4))?;
+        self.writer.write_all(&data_len.to_le_bytes())?;
+        self.writer.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emu_type::EmuType;
+    use crate::test_utils::*;
+
+    fn read_u32(data: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn test_render_to_wav() {
+        let path = temp_path("render_to_wav.wav");
+        let mut emu = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        let mut reports = 0;
+        let mut last = None;
+        let mut options = RenderOptions::default()
+            .limit(PlaybackLimit::Fixed(Duration::from_secs(1)))
+            .fade_length(Duration::from_millis(500))
+            .on_progress(|progress| {
+                reports += 1;
+                last = Some(progress);
+            });
+        render_to_wav(&mut emu, &path, 0, &mut options).unwrap();
+        drop(options);
+
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&data[..4], b"RIFF");
+        assert_eq!(read_u32(&data, 4) as usize, data.len() - 8);
+        assert_eq!(&data[36..40], b"data");
+        assert_eq!(read_u32(&data, 40) as usize, data.len() - 44);
+        // Up to 1.5 seconds of stereo 16-bit samples. Game Music Emu ends the track once the
+        // fade is inaudible, which can be a little early.
+        let frames = (data.len() - 44) / 4;
+        assert!((44100..=66150).contains(&frames), "{frames}");
+        assert!(data[44..].iter().any(|&byte| byte != 0));
+
+        assert!(reports > 1);
+        let last = last.unwrap();
+        assert!(last.total <= Duration::from_millis(1500));
+        assert_eq!(last.rendered, last.total);
+        assert_eq!(last.fraction(), 1.0);
+        assert_eq!(emu.playback_limit(), PlaybackLimit::Auto);
+    }
+
+    #[test]
+    fn test_render_float() {
+        let path = temp_path("render_float.wav");
+        let mut emu = GameMusicEmu::from_file(TEST_NSF_PATH, 22050).unwrap();
+        let mut options = RenderOptions::default()
+            .format(WavFormat::Float32)
+            .limit(PlaybackLimit::Fixed(Duration::from_millis(200)))
+            .fade_length(Duration::ZERO);
+        render_to_wav(&mut emu, &path, 0, &mut options).unwrap();
+
+        let data = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(u16::from_le_bytes([data[20], data[21]]), 3);
+        assert_eq!(&data[38..42], b"fact");
+        assert_eq!(read_u32(&data, 46), 4410);
+        assert_eq!(&data[50..54], b"data");
+        assert_eq!(read_u32(&data, 54), 4410 * 8);
+        assert_eq!(data.len(), 58 + 4410 * 8);
+    }
+
+    #[test]
+    fn test_infinite_is_rejected() {
+        let mut emu = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        let mut options = RenderOptions::default().limit(PlaybackLimit::Infinite);
+        let path = temp_path("infinite.wav");
+        let err = render_to_wav(&mut emu, &path, 0, &mut options).unwrap_err();
+        assert!(
+            matches!(err, GmeOrIoError::Gme(err) if err.kind() == GmeErrorKind::InvalidArgument)
+        );
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_multi_channel_is_rejected() {
+        let mut emu = GameMusicEmu::new_multi_channel(EmuType::Nsf, 44100).unwrap();
+        emu.load_file(TEST_NSF_PATH).unwrap();
+        let path = temp_path("multi_channel.wav");
+        let err = render_to_wav(&mut emu, &path, 0, &mut RenderOptions::default()).unwrap_err();
+        assert!(matches!(err, GmeOrIoError::Gme(err) if err.kind() == GmeErrorKind::Unsupported));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn test_track_file_name() {
+        let info = EmuTrackInfo {
+            song: Some("Title: A/B?".into()),
+            ..EmuTrackInfo::default()
+        };
+        assert_eq!(track_file_name(2, &info), "03 - Title_ A_B_.wav");
+        assert_eq!(track_file_name(0, &EmuTrackInfo::default()), "01.wav");
+    }
+}
@@ -0,0 +1,67 @@
+/// Ramps the volume up from silence at the start of a track and after seeking. Game Music Emu
+/// only fades out, so this is applied to samples after they are generated.
+#[derive(Clone, Default, Debug)]
+pub(crate) struct FadeIn {
+    /// Length of the fade in frames. 0 disables it.
+    length: u32,
+    /// Frames of the fade played so far
+    position: u32,
+}
+
+impl FadeIn {
+    pub(crate) fn new(length: u32) -> Self {
+        Self {
+            length,
+            position: length,
+        }
+    }
+
+    /// Start fading in from silence again
+    pub(crate) fn restart(&mut self) {
+        self.position = 0;
+    }
+
+    /// Applies the fade to interleaved samples with `channels` samples per frame
+    pub(crate) fn apply(&mut self, buffer: &mut [i16], channels: usize) {
+        for frame in buffer.chunks_exact_mut(channels) {
+            if self.position >= self.length {
+                break;
+            }
+            // Squaring the ramp makes it sound closer to linear in loudness
+            let gain = (self.position as f32 / self.length as f32).powi(2);
+            for sample in frame {
+                *sample = (*sample as f32 * gain) as i16;
+            }
+            self.position += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply() {
+        let mut fade_in = FadeIn::new(4);
+        fade_in.restart();
+        let mut buffer = [1000_i16; 12];
+        fade_in.apply(&mut buffer, 2);
+        assert_eq!(
+            buffer,
+            [0, 0, 62, 62, 250, 250, 562, 562, 1000, 1000, 1000, 1000]
+        );
+        let mut buffer = [1000_i16; 4];
+        fade_in.apply(&mut buffer, 2);
+        assert_eq!(buffer, [1000; 4]);
+    }
+
+    #[test]
+    fn test_disabled() {
+        let mut fade_in = FadeIn::default();
+        fade_in.restart();
+        let mut buffer = [1000_i16; 4];
+        fade_in.apply(&mut buffer, 2);
+        assert_eq!(buffer, [1000; 4]);
+    }
+}
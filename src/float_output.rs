@@ -0,0 +1,78 @@
+/// Converts 16-bit samples to `f32` with a gain, then limits them to -1.0..=1.0
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub(crate) struct FloatOutput {
+    pub(crate) gain: f32,
+    /// Compress peaks smoothly instead of clipping them hard at full scale
+    pub(crate) soft_clip: bool,
+}
+
+/// Level above which soft clipping starts compressing
+const SOFT_CLIP_THRESHOLD: f32 = 0.5;
+
+impl Default for FloatOutput {
+    fn default() -> Self {
+        Self {
+            gain: 1.0,
+            soft_clip: false,
+        }
+    }
+}
+
+impl FloatOutput {
+    /// Converts `input` into the start of `output`, which must be at least as long
+    pub(crate) fn convert(&self, input: &[i16], output: &mut [f32]) {
+        for (out, &sample) in output.iter_mut().zip(input) {
+            let value = sample as f32 / 32768.0 * self.gain;
+            *out = if self.soft_clip {
+                soft_clip(value)
+            } else {
+                value.clamp(-1.0, 1.0)
+            };
+        }
+    }
+}
+
+/// Leaves quiet samples alone and bends louder ones towards full scale, so the curve has no
+/// corner where the compression starts
+fn soft_clip(value: f32) -> f32 {
+    let magnitude = value.abs();
+    if magnitude <= SOFT_CLIP_THRESHOLD {
+        return value;
+    }
+    let headroom = 1.0 - SOFT_CLIP_THRESHOLD;
+    let compressed =
+        SOFT_CLIP_THRESHOLD + headroom * ((magnitude - SOFT_CLIP_THRESHOLD) / headroom).tanh();
+    compressed.copysign(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert() {
+        let mut output = [0.0; 4];
+        FloatOutput::default().convert(&[0, 16384, -32768, 32767], &mut output);
+        assert_eq!(output[..3], [0.0, 0.5, -1.0]);
+        assert!((output[3] - 1.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_gain_clips() {
+        let output_with = |soft_clip| {
+            let mut output = [0.0; 3];
+            let float_output = FloatOutput {
+                gain: 4.0,
+                soft_clip,
+            };
+            float_output.convert(&[4096, 16384, -32768], &mut output);
+            output
+        };
+        assert_eq!(output_with(false), [0.5, 1.0, -1.0]);
+
+        let soft = output_with(true);
+        assert_eq!(soft[0], 0.5);
+        assert!(soft[1] > 0.9 && soft[1] < 1.0);
+        assert!(soft[2] < -0.99 && soft[2] >= -1.0);
+    }
+}
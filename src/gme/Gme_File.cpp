@@ -2,6 +2,8 @@
 
 #include "Gme_File.h"
 
+#include "Music_Emu.h"
+
 #include "blargg_endian.h"
 #include <string.h>
 
@@ -33,6 +35,7 @@ void Gme_File::unload()
 	track_count_     = 0;
 	raw_track_count_ = 0;
 	file_data.clear();
+	track_infos.clear();
 }
 
 Gme_File::Gme_File()
@@ -108,7 +111,29 @@ blargg_err_t Gme_File::load_tracks( void const* in, long* sizes, int count )
 	tracks[count] = size;
 	RETURN_ERR( file_data.resize( size ) );
 	memcpy( file_data.begin(), in, size );
-	return post_load( load_mem_( file_data.begin(), tracks[1] ) );
+	blargg_err_t err = load_track_infos_();
+	if ( !err )
+		err = load_mem_( file_data.begin(), tracks[1] );
+	return post_load( err );
+}
+
+// Reads the info of each track from its own file, since track_info_() only sees the loaded track
+blargg_err_t Gme_File::load_track_infos_()
+{
+	int count = tracks.size() - 1;
+	RETURN_ERR( track_infos.resize( count ) );
+	Music_Emu* info = type()->new_info();
+	if ( !info )
+		return "Out of memory";
+	blargg_err_t err = 0;
+	for ( int i = 0; i < count && !err; i++ )
+	{
+		err = info->load_mem( track_pos( i ), track_size( i ) );
+		if ( !err )
+			err = info->track_info( &track_infos [i], 0 );
+	}
+	delete info;
+	return err;
 }
 
 blargg_err_t Gme_File::load( Data_Reader& in )
@@ -227,7 +252,15 @@ blargg_err_t Gme_File::track_info( track_info_t* out, int track ) const
 
 	int remapped = track;
 	RETURN_ERR( remap_track_( &remapped ) );
-	RETURN_ERR( track_info_( out, remapped ) );
+	if ( track_infos.size() )
+	{
+		*out = track_infos [remapped];
+		out->track_count = track_count();
+	}
+	else
+	{
+		RETURN_ERR( track_info_( out, remapped ) );
+	}
 
 	// override with m3u info
 	if ( playlist.size() )
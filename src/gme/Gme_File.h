@@ -160,8 +160,10 @@ private:
 	char playlist_warning [64];
 	blargg_vector<byte> file_data; // only if loaded into memory using default load
 	blargg_vector<long> tracks;    // file start indexes of `file_data`
+	blargg_vector<track_info_t> track_infos; // only if loaded using load_tracks()
 
 	blargg_err_t load_m3u_( blargg_err_t );
+	blargg_err_t load_track_infos_();
 	blargg_err_t post_load( blargg_err_t err );
 public:
 	// track_info field copying
@@ -0,0 +1,198 @@
+use crate::emu_type::EmuType;
+use crate::error::{GmeError, GmeOrIoError};
+use crate::playback_limit::PlaybackLimit;
+use crate::stems::STEM_COUNT;
+use crate::wrapper::GameMusicEmu;
+use rodio::source::SeekError;
+use rodio::{ChannelCount, SampleRate, Source};
+use std::path::Path;
+use std::time::Duration;
+
+/// Sample rate [GmeSource::open] creates the emulator with
+pub const GME_SOURCE_SAMPLE_RATE: u32 = 44100;
+
+/// Samples generated at a time. A multiple of 16 so multi-channel emulators get whole frames.
+const BUFFER_LEN: usize = 2048;
+
+/// A [rodio::Source] that plays a track of a [GameMusicEmu]. It ends when the track ends, as
+/// decided by the emulator's [PlaybackLimit], or when the emulator returns an error, which
+/// [GmeSource::take_error] returns.
+///
+/// ```no_run
+/// use game_music_emu::GmeSource;
+///
+/// let stream = rodio::OutputStreamBuilder::open_default_stream()?;
+/// let sink = rodio::Sink::connect_new(stream.mixer());
+/// sink.append(GmeSource::open("assets/test.nsf", 0)?);
+/// sink.sleep_until_end();
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub struct GmeSource {
+    emu: GameMusicEmu,
+    buffer: Box<[f32]>,
+    /// Index of the next sample in `buffer` to return
+    position: usize,
+    total_duration: Option<Duration>,
+    error: Option<GmeError>,
+}
+
+impl GmeSource {
+    /// Load the file at `path` and start `track`, at [GME_SOURCE_SAMPLE_RATE]
+    pub fn open(path: impl AsRef<Path>, track: usize) -> Result<Self, GmeOrIoError> {
+        let mut emu = GameMusicEmu::from_file(path, GME_SOURCE_SAMPLE_RATE)?;
+        emu.start_track(track)?;
+        Ok(Self::new(emu))
+    }
+
+    /// Play the current track of `emu`, which should already be configured and started
+    pub fn new(emu: GameMusicEmu) -> Self {
+        let total_duration = total_duration(&emu);
+        Self {
+            emu,
+            buffer: vec![0.0; BUFFER_LEN].into_boxed_slice(),
+            position: BUFFER_LEN,
+            total_duration,
+            error: None,
+        }
+    }
+
+    /// The error that ended the source, if the emulator failed while playing
+    pub fn take_error(&mut self) -> Option<GmeError> {
+        self.error.take()
+    }
+
+    pub fn emu(&self) -> &GameMusicEmu {
+        &self.emu
+    }
+
+    /// Samples that were already generated still play after changing the emulator
+    pub fn emu_mut(&mut self) -> &mut GameMusicEmu {
+        &mut self.emu
+    }
+
+    pub fn into_inner(self) -> GameMusicEmu {
+        self.emu
+    }
+}
+
+/// Length of the current track, if it is known. A fixed limit includes the fade out. Game Music
+/// Emu only ends SPC tracks at the length from the file, which doesn't include their 50 ms fade.
+fn total_duration(emu: &GameMusicEmu) -> Option<Duration> {
+    let info = emu.track_info(emu.current_track()? as u32).ok()?;
+    match emu.playback_limit() {
+        PlaybackLimit::Auto if emu.emu_type() == EmuType::Spc => info.length,
+        PlaybackLimit::Auto => None,
+        PlaybackLimit::Infinite => None,
+        limit => Some(limit.fade_start(&info)? + emu.playback_fade_length()),
+    }
+}
+
+impl Iterator for GmeSource {
+    type Item = f32;
+
+    fn next(&mut self) -> Option<f32> {
+        if self.position == self.buffer.len() {
+            if self.emu.track_ended() {
+                return None;
+            }
+            if let Err(err) = self.emu.play_f32(&mut self.buffer) {
+                self.error = Some(err);
+                return None;
+            }
+            self.position = 0;
+        }
+        let sample = self.buffer[self.position];
+        self.position += 1;
+        Some(sample)
+    }
+}
+
+impl Source for GmeSource {
+    fn current_span_len(&self) -> Option<usize> {
+        // Each buffer is its own span, so rodio reads the sample rate again after a change
+        // through [GmeSource::emu_mut]
+        if self.position < self.buffer.len() {
+            Some(self.buffer.len() - self.position)
+        } else if self.emu.track_ended() {
+            Some(0)
+        } else {
+            Some(self.buffer.len())
+        }
+    }
+
+    fn channels(&self) -> ChannelCount {
+        if self.emu.is_multi_channel() {
+            STEM_COUNT as ChannelCount * 2
+        } else {
+            2
+        }
+    }
+
+    fn sample_rate(&self) -> SampleRate {
+        self.emu.sample_rate()
+    }
+
+    fn total_duration(&self) -> Option<Duration> {
+        self.total_duration
+    }
+
+    fn try_seek(&mut self, position: Duration) -> Result<(), SeekError> {
+        self.emu
+            .seek(position)
+            .map_err(|err| SeekError::Other(Box::new(err)))?;
+        self.position = self.buffer.len();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_source() {
+        let mut source = GmeSource::open(TEST_NSF_PATH, 0).unwrap();
+        assert_eq!(source.channels(), 2);
+        assert_eq!(source.sample_rate(), GME_SOURCE_SAMPLE_RATE);
+        assert!(source.by_ref().take(10000).any(|sample| sample != 0.0));
+
+        source.try_seek(Duration::from_secs(3)).unwrap();
+        assert_eq!(source.emu().tell(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn test_sample_rate_change_starts_span() {
+        let mut source = GmeSource::open(TEST_NSF_PATH, 0).unwrap();
+        assert_eq!(source.current_span_len(), Some(BUFFER_LEN));
+        source.by_ref().take(100).for_each(drop);
+        assert_eq!(source.current_span_len(), Some(BUFFER_LEN - 100));
+
+        source.emu_mut().set_sample_rate(48000).unwrap();
+        source.by_ref().take(BUFFER_LEN - 100).for_each(drop);
+        assert_eq!(source.sample_rate(), 48000);
+        assert_eq!(source.current_span_len(), Some(BUFFER_LEN));
+    }
+
+    #[test]
+    fn test_auto_duration_is_unknown() {
+        // Only SPC tracks end at the length from the file, so other tracks have no known end
+        let source = GmeSource::open(TEST_NSF_PATH, 0).unwrap();
+        assert_eq!(source.emu().playback_limit(), PlaybackLimit::Auto);
+        assert_eq!(source.total_duration(), None);
+    }
+
+    #[test]
+    fn test_ends_with_track() {
+        let mut emu = GameMusicEmu::from_file(TEST_NSF_PATH, GME_SOURCE_SAMPLE_RATE).unwrap();
+        emu.set_playback_fade_length(Duration::ZERO);
+        emu.set_playback_limit(PlaybackLimit::Fixed(Duration::from_millis(500)));
+        emu.start_track(0).unwrap();
+        let mut source = GmeSource::new(emu);
+        assert_eq!(source.total_duration(), Some(Duration::from_millis(500)));
+        let samples = source.by_ref().count();
+        // Game Music Emu notices the end after the fade, in whole blocks
+        assert!((44100..4 * 44100).contains(&samples), "{samples}");
+        assert!(source.take_error().is_none());
+    }
+}
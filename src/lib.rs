@@ -18,28 +18,61 @@
 //!```rust
 //!use game_music_emu::{EmuType, GameMusicEmu};
 //!
-//!let emu = GameMusicEmu::new(EmuType::Nsf, 44100);
+//!let mut emu = GameMusicEmu::new(EmuType::Nsf, 44100)?;
 //!emu.load_file("assets/test.nsf")?;
 //!emu.start_track(0);
 //! # Ok::<(), game_music_emu::GmeOrIoError>(())
 //!```
 //!
-//! There is also an [example](examples/play_nsf.rs) that plays a song.
+//! There is also an [example](examples/play_nsf.rs) that plays a song with the [player] module,
+//! which needs the `cpal` feature.
 //!
 
 #![deny(unused_must_use)]
 
 pub use self::{
+    emu_equalizer::{EmuEqualizer, EqualizerPreset},
+    emu_track_info::EmuTrackInfo,
     emu_type::*,
     error::*,
-    native::{identify_header, type_list},
-    wrapper::GameMusicEmu,
+    native::{identify_extension, identify_file, identify_header, type_list},
+    playback_limit::PlaybackLimit,
+    playback_settings::PlaybackSettings,
+    sample_position::SamplePosition,
+    sample_stream::SampleStream,
+    shared_emu::SharedGameMusicEmu,
+    stems::{STEM_COUNT, StemFrame},
+    voice_mask::VoiceMask,
+    wrapper::{DEFAULT_FADE_LENGTH, GameMusicEmu, SAMPLE_RATES},
 };
 
+#[cfg(feature = "rodio")]
+pub use self::gme_source::{GME_SOURCE_SAMPLE_RATE, GmeSource};
+#[cfg(feature = "spc")]
+pub use self::spc_options::SpcOptions;
+
 mod emu_equalizer;
 mod emu_track_info;
 mod emu_type;
 mod error;
+pub mod export;
+mod fade_in;
+mod float_output;
+#[cfg(feature = "rodio")]
+mod gme_source;
 mod native;
+mod playback_limit;
+mod playback_settings;
+#[cfg(feature = "cpal")]
+pub mod player;
+mod resampler;
+mod sample_position;
+mod sample_stream;
+pub mod scan;
+mod shared_emu;
+#[cfg(feature = "spc")]
+mod spc_options;
+mod stems;
 pub mod test_utils;
+mod voice_mask;
 mod wrapper;
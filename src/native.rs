@@ -1,87 +1,291 @@
 use crate::emu_equalizer::EmuEqualizer;
 use crate::emu_track_info::EmuTrackInfo;
 use crate::emu_type::EmuType;
-use crate::error::{GmeError, GmeOrIoError, GmeResult};
-use std::ffi::{CStr, CString};
+use crate::error::{GmeError, GmeErrorKind, GmeOrIoError, GmeResult};
+use std::ffi::{CStr, CString, c_long, c_void};
+use std::io::Read;
 use std::os::raw::c_char;
+use std::panic::{self, AssertUnwindSafe};
 use std::path::Path;
-use std::sync::Arc;
+use std::ptr::NonNull;
+use std::time::Duration;
 
-/// Holds a pointer to a `MusicEmu` instance in the C++ code. It automatically frees the instance
+/// Owns a pointer to a `MusicEmu` instance in the C++ code. It automatically frees the instance
 /// when dropped.
-#[derive(Clone)]
+///
+/// Functions that mutate the emulator take `&mut EmuHandle`, so the borrow checker guarantees
+/// that nothing else is using the instance while they run.
 pub(crate) struct EmuHandle {
-    pub(crate) emu: Arc<MusicEmu>,
+    emu: NonNull<MusicEmu>,
 }
 
+// SAFETY: The handle is the only owner of the emulator, and the emulator has no thread affinity,
+// so moving it to another thread is fine. It is not `Sync` because Game Music Emu does not
+// synchronize access to an instance.
+unsafe impl Send for EmuHandle {}
+
 impl EmuHandle {
-    pub(crate) fn new(emu: *const MusicEmu) -> Self {
-        #[allow(clippy::crosspointer_transmute)]
-        unsafe {
-            Self {
-                emu: Arc::new(std::mem::transmute::<*const MusicEmu, MusicEmu>(emu)),
-            }
-        }
+    /// Takes ownership of `emu`. Returns `None` if it is null.
+    pub(crate) fn new(emu: *mut MusicEmu) -> Option<Self> {
+        NonNull::new(emu).map(|emu| Self { emu })
     }
 
     pub(crate) fn to_raw(&self) -> *const MusicEmu {
-        unsafe { std::mem::transmute_copy(&*self.emu) }
+        self.emu.as_ptr()
+    }
+
+    pub(crate) fn to_raw_mut(&mut self) -> *mut MusicEmu {
+        self.emu.as_ptr()
     }
 }
 
 impl Drop for EmuHandle {
     fn drop(&mut self) {
-        if Arc::strong_count(&self.emu) == 1 {
-            delete(self);
+        unsafe {
+            gme_delete(self.to_raw_mut());
         }
     }
 }
 
-pub(crate) fn delete(handle: &EmuHandle) {
+/// Determine likely `EmuType` based on first four bytes of file. Returns `None` if the header is
+/// not recognized or `buffer` is shorter than four bytes.
+pub fn identify_header(buffer: &[u8]) -> Option<EmuType> {
+    if buffer.len() < 4 {
+        return None;
+    }
     unsafe {
-        gme_delete(handle.to_raw());
+        let extension = CStr::from_ptr(gme_identify_header(buffer.as_ptr()));
+        EmuType::from_extension(extension.to_str().ok()?)
     }
 }
 
-/// Determine likely `EmuType` based on first four bytes of file.
-pub fn identify_header(buffer: &[u8]) -> EmuType {
+/// Determine `EmuType` based on a file's extension, or its header if the extension isn't
+/// recognized. Returns `None` if neither is recognized.
+pub fn identify_file(path: impl AsRef<Path>) -> GmeResult<Option<EmuType>> {
+    let cstring = path_to_cstring(path.as_ref())?;
+    let mut gme_type: gme_type_t = std::ptr::null();
     unsafe {
-        EmuType::from_extension(
-            CStr::from_ptr(gme_identify_header(buffer.as_ptr()))
-                .to_str()
-                .unwrap(),
-        )
+        process_result(gme_identify_file(cstring.as_ptr(), &mut gme_type))?;
+        Ok(to_emu_type(gme_type))
+    }
+}
+
+/// Get the `EmuType` for a file path or extension, ignoring case. Only types the crate was
+/// compiled with are recognized.
+pub fn identify_extension(path_or_extension: &str) -> Option<EmuType> {
+    let cstring = CString::new(path_or_extension).ok()?;
+    unsafe { to_emu_type(gme_identify_extension(cstring.as_ptr())) }
+}
+
+/// Converts a `gme_type_t` to its `EmuType`. Returns `None` if it is null.
+unsafe fn to_emu_type(gme_type: gme_type_t) -> Option<EmuType> {
+    if gme_type.is_null() {
+        return None;
+    }
+    unsafe {
+        let extension = CStr::from_ptr(gme_type_extension(gme_type));
+        EmuType::from_extension(extension.to_str().ok()?)
     }
 }
 
 /// Load music file from memory into emulator. Makes a copy of data passed.
-pub(crate) fn load_data(handle: &EmuHandle, data: &[u8]) -> GmeResult<()> {
+pub(crate) fn load_data(handle: &mut EmuHandle, data: &[u8]) -> GmeResult<()> {
     unsafe {
         // let mut emu_ptr: *const MusicEmu = std::ptr::null_mut();
-        process_result(gme_load_data(handle.to_raw(), data.as_ptr(), data.len()))
+        process_result(gme_load_data(
+            handle.to_raw_mut(),
+            data.as_ptr(),
+            data.len(),
+        ))
     }
 }
 
 /// Load music file into emulator
-pub(crate) fn load_file(handle: &EmuHandle, path: impl AsRef<Path>) -> Result<(), GmeOrIoError> {
+pub(crate) fn load_file(
+    handle: &mut EmuHandle,
+    path: impl AsRef<Path>,
+) -> Result<(), GmeOrIoError> {
     let buffer = get_file_data(path)?;
     Ok(load_data(handle, &buffer)?)
 }
 
-/// Creates an `EmuHandle` with the specified `EmuType`
-pub(crate) fn new_emu(emu_type: EmuType, sample_rate: u32) -> EmuHandle {
+/// Load several single-track music files as the tracks of one emulator
+pub(crate) fn load_tracks(handle: &mut EmuHandle, tracks: &[&[u8]]) -> GmeResult<()> {
+    if tracks.is_empty() {
+        return Err(GmeError::with_kind(
+            GmeErrorKind::InvalidArgument,
+            "At least one track is required",
+        ));
+    }
+    let count = i32::try_from(tracks.len())
+        .map_err(|_| GmeError::with_kind(GmeErrorKind::InvalidArgument, "Too many tracks"))?;
+    // gme expects the files back to back in one buffer
+    let data = tracks.concat();
+    let mut sizes = tracks
+        .iter()
+        .map(|track| track.len() as c_long)
+        .collect::<Vec<_>>();
     unsafe {
-        let cstring = CString::new(emu_type.to_extension()).unwrap();
-        let gme_type = gme_identify_extension(cstring.as_ptr());
-        let music_emu = gme_new_emu(gme_type, sample_rate as i32);
-        EmuHandle::new(music_emu)
+        process_result(gme_load_tracks(
+            handle.to_raw_mut(),
+            data.as_ptr(),
+            sizes.as_mut_ptr(),
+            count,
+        ))
+    }
+}
+
+/// Load a music file of `size` bytes into the emulator by reading it from `reader` as the
+/// emulator needs it
+pub(crate) fn load_reader(
+    handle: &mut EmuHandle,
+    reader: &mut dyn Read,
+    size: u64,
+) -> Result<(), GmeOrIoError> {
+    let size = c_long::try_from(size).map_err(|_| {
+        GmeError::with_kind(
+            GmeErrorKind::InvalidArgument,
+            format!("File size {} is too large", size),
+        )
+    })?;
+    let mut state = ReaderState {
+        reader,
+        error: None,
+        panic: None,
+    };
+    let result = unsafe {
+        process_result(gme_load_custom(
+            handle.to_raw_mut(),
+            read_callback,
+            size,
+            &mut state as *mut ReaderState as *mut c_void,
+        ))
+    };
+    if let Some(payload) = state.panic {
+        panic::resume_unwind(payload);
+    }
+    // The IO error explains more than the message the callback returned for it
+    if let Some(err) = state.error {
+        return Err(err.into());
     }
+    Ok(result?)
+}
+
+/// Passed through `gme_load_custom` to `read_callback`
+struct ReaderState<'a> {
+    reader: &'a mut dyn Read,
+    error: Option<std::io::Error>,
+    /// A panic from `reader`, which can't unwind through the C++ code
+    panic: Option<Box<dyn std::any::Any + Send>>,
+}
+
+unsafe extern "C" fn read_callback(
+    user_data: *mut c_void,
+    out: *mut c_void,
+    count: i32,
+) -> *const c_char {
+    // SAFETY: `user_data` is the `ReaderState` passed by `load_reader`, and gme asks for at most
+    // `count` bytes into `out`
+    let state = unsafe { &mut *(user_data as *mut ReaderState) };
+    let out = unsafe { std::slice::from_raw_parts_mut(out as *mut u8, count.max(0) as usize) };
+    match panic::catch_unwind(AssertUnwindSafe(|| state.reader.read_exact(out))) {
+        Ok(Ok(())) => std::ptr::null(),
+        Ok(Err(err)) => {
+            state.error = Some(err);
+            c"Read error".as_ptr()
+        }
+        Err(payload) => {
+            state.panic = Some(payload);
+            c"Read error".as_ptr()
+        }
+    }
+}
+
+/// Gets the `gme_type_t` for an `EmuType`. Returns `None` if it was not compiled in.
+fn to_gme_type(emu_type: EmuType) -> Option<gme_type_t> {
+    let cstring = CString::new(emu_type.to_extension()).unwrap();
+    let gme_type = unsafe { gme_identify_extension(cstring.as_ptr()) };
+    (!gme_type.is_null()).then_some(gme_type)
+}
+
+/// Creates an `EmuHandle` with the specified `EmuType`
+pub(crate) fn new_emu(emu_type: EmuType, sample_rate: u32) -> GmeResult<EmuHandle> {
+    let gme_type = compiled_gme_type(emu_type)?;
+    let music_emu = unsafe { gme_new_emu(gme_type, sample_rate as i32) };
+    EmuHandle::new(music_emu)
+        .ok_or_else(|| GmeError::with_kind(GmeErrorKind::OutOfMemory, "Out of memory"))
+}
+
+/// Creates an emulator that renders each of its first 8 voices into its own stereo pair.
+/// Fails if `emu_type` doesn't support multi-channel rendering.
+pub(crate) fn new_emu_multi_channel(emu_type: EmuType, sample_rate: u32) -> GmeResult<EmuHandle> {
+    let gme_type = compiled_gme_type(emu_type)?;
+    let music_emu = unsafe { gme_new_emu_multi_channel(gme_type, sample_rate as i32) };
+    let handle = EmuHandle::new(music_emu)
+        .ok_or_else(|| GmeError::with_kind(GmeErrorKind::OutOfMemory, "Out of memory"))?;
+    // Types that can't render multi-channel silently fall back to stereo
+    if !multi_channel(&handle) {
+        return Err(GmeError::with_kind(
+            GmeErrorKind::Unsupported,
+            format!(
+                "Emulator type {} doesn't support multi-channel rendering",
+                emu_type.to_extension()
+            ),
+        ));
+    }
+    Ok(handle)
+}
+
+/// True if the emulator renders each voice into its own stereo pair
+pub(crate) fn multi_channel(handle: &EmuHandle) -> bool {
+    unsafe { gme_multi_channel(handle.to_raw()) != 0 }
+}
+
+/// Number of interleaved samples per frame that `play` generates
+pub(crate) fn out_channels(handle: &EmuHandle) -> usize {
+    if multi_channel(handle) { 16 } else { 2 }
+}
+
+fn compiled_gme_type(emu_type: EmuType) -> GmeResult<gme_type_t> {
+    to_gme_type(emu_type).ok_or_else(|| {
+        GmeError::with_kind(
+            GmeErrorKind::Unsupported,
+            format!(
+                "Emulator type {} was not compiled in",
+                emu_type.to_extension()
+            ),
+        )
+    })
+}
+
+/// Name of the system an `EmuType` is generally for
+pub(crate) fn type_system(emu_type: EmuType) -> Option<&'static str> {
+    let gme_type = to_gme_type(emu_type)?;
+    unsafe { CStr::from_ptr(gme_type_system(gme_type)).to_str().ok() }
+}
+
+/// True if an `EmuType` supports multiple tracks
+pub(crate) fn type_multitrack(emu_type: EmuType) -> Option<bool> {
+    let gme_type = to_gme_type(emu_type)?;
+    unsafe { Some(gme_type_multitrack(gme_type) != 0) }
+}
+
+/// The fixed track count of an `EmuType`, where 0 means it varies by file
+pub(crate) fn fixed_track_count(emu_type: EmuType) -> Option<usize> {
+    let gme_type = to_gme_type(emu_type)?;
+    unsafe { Some(gme_fixed_track_count(gme_type) as usize) }
+}
+
+/// The `EmuType` of a loaded emulator
+pub(crate) fn emu_type(handle: &EmuHandle) -> EmuType {
+    unsafe { to_emu_type(gme_type(handle.to_raw())) }
+        .expect("emulators are only created for known types")
 }
 
 pub(crate) fn open_data(data: &[u8], sample_rate: u32) -> GmeResult<EmuHandle> {
-    let emu_type = identify_header(data);
-    let handle = new_emu(emu_type, sample_rate);
-    load_data(&handle, data)?;
+    let emu_type = identify_header(data).ok_or_else(wrong_file_type)?;
+    let mut handle = new_emu(emu_type, sample_rate)?;
+    load_data(&mut handle, data)?;
     Ok(handle)
 }
 
@@ -93,52 +297,116 @@ pub(crate) fn open_file(
     Ok(open_data(&buffer, sample_rate)?)
 }
 
-pub(crate) fn play(handle: &EmuHandle, count: usize, buffer: &mut [i16]) -> Result<(), GmeError> {
-    unsafe { process_result(gme_play(handle.to_raw(), count as i32, buffer.as_mut_ptr())) }
+/// Largest number of samples passed to a single `gme_play` call. It takes an `int` count, so
+/// larger buffers are filled in chunks. Kept a multiple of 16 so chunks never split a frame,
+/// even in multi-channel mode.
+const MAX_PLAY_COUNT: usize = i32::MAX as usize & !15;
+
+/// Checks that a buffer of `len` samples holds whole frames
+pub(crate) fn check_buffer_len(handle: &EmuHandle, len: usize) -> GmeResult<()> {
+    let channels = out_channels(handle);
+    if !len.is_multiple_of(channels) {
+        return Err(GmeError::with_kind(
+            GmeErrorKind::InvalidBufferLength,
+            format!(
+                "Buffer length {} is not a multiple of {} (channels)",
+                len, channels
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Fills all of `buffer` with interleaved samples, 2 per frame in stereo or 16 in multi-channel
+/// mode. Its length must be a whole number of frames.
+pub(crate) fn play(handle: &mut EmuHandle, buffer: &mut [i16]) -> GmeResult<()> {
+    check_buffer_len(handle, buffer.len())?;
+    for chunk in buffer.chunks_mut(MAX_PLAY_COUNT) {
+        unsafe {
+            process_result(gme_play(
+                handle.to_raw_mut(),
+                chunk.len() as i32,
+                chunk.as_mut_ptr(),
+            ))?;
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn start_track(handle: &mut EmuHandle, index: u32) -> GmeResult<()> {
+    unsafe { process_result(gme_start_track(handle.to_raw_mut(), index as i32)) }
 }
 
-pub(crate) fn start_track(handle: &EmuHandle, index: u32) -> GmeResult<()> {
-    unsafe { process_result(gme_start_track(handle.to_raw(), index as i32)) }
+/// Most recent warning, or `None` if there is none. Clears the warning.
+pub(crate) fn warning(handle: &mut EmuHandle) -> Option<String> {
+    unsafe {
+        let ptr = gme_warning(handle.to_raw_mut());
+        if ptr.is_null() {
+            None
+        } else {
+            Some(CStr::from_ptr(ptr).to_string_lossy().into_owned())
+        }
+    }
 }
 
 pub(crate) fn tell(handle: &EmuHandle) -> u32 {
     unsafe { gme_tell(handle.to_raw()) as u32 }
 }
 
+pub(crate) fn tell_samples(handle: &EmuHandle) -> u32 {
+    unsafe { gme_tell_samples(handle.to_raw()) as u32 }
+}
+
+pub(crate) fn tell_scaled(handle: &EmuHandle) -> u32 {
+    unsafe { gme_tell_scaled(handle.to_raw()) as u32 }
+}
+
 pub(crate) fn track_count(handle: &EmuHandle) -> usize {
     unsafe { gme_track_count(handle.to_raw()) as usize }
 }
 
 pub(crate) fn track_ended(handle: &EmuHandle) -> bool {
-    unsafe { gme_track_ended(handle.to_raw()) }
+    unsafe { gme_track_ended(handle.to_raw()) != 0 }
+}
+
+pub(crate) fn seek(handle: &mut EmuHandle, msec: u32) -> GmeResult<()> {
+    unsafe { process_result(gme_seek(handle.to_raw_mut(), msec as i32)) }
+}
+
+pub(crate) fn seek_samples(handle: &mut EmuHandle, count: u32) -> GmeResult<()> {
+    unsafe { process_result(gme_seek_samples(handle.to_raw_mut(), count as i32)) }
+}
+
+pub(crate) fn seek_scaled(handle: &mut EmuHandle, msec: u32) -> GmeResult<()> {
+    unsafe { process_result(gme_seek_scaled(handle.to_raw_mut(), msec as i32)) }
 }
 
-pub(crate) fn seek(handle: &EmuHandle, msec: u32) -> GmeResult<()> {
-    unsafe { process_result(gme_seek(handle.to_raw(), msec as i32)) }
+pub(crate) fn set_fade_msecs(handle: &mut EmuHandle, start_msec: i32, length_msec: i32) {
+    unsafe { gme_set_fade_msecs(handle.to_raw_mut(), start_msec, length_msec) }
 }
 
-pub(crate) fn set_fade(handle: &EmuHandle, start_msec: u32) {
-    unsafe { gme_set_fade(handle.to_raw(), start_msec as i32) }
+pub(crate) fn set_autoload_playback_limit(handle: &mut EmuHandle, autoload: bool) {
+    unsafe { gme_set_autoload_playback_limit(handle.to_raw_mut(), autoload as i32) }
 }
 
-pub(crate) fn set_stereo_depth(handle: &EmuHandle, depth: f64) {
-    unsafe { gme_set_stereo_depth(handle.to_raw(), depth) }
+pub(crate) fn set_stereo_depth(handle: &mut EmuHandle, depth: f64) {
+    unsafe { gme_set_stereo_depth(handle.to_raw_mut(), depth) }
 }
 
-pub(crate) fn ignore_silence(handle: &EmuHandle, ignore: bool) {
-    unsafe { gme_ignore_silence(handle.to_raw(), ignore as i32) }
+pub(crate) fn ignore_silence(handle: &mut EmuHandle, ignore: bool) {
+    unsafe { gme_ignore_silence(handle.to_raw_mut(), ignore as i32) }
 }
 
-pub(crate) fn set_tempo(handle: &EmuHandle, tempo: f64) {
-    unsafe { gme_set_tempo(handle.to_raw(), tempo) }
+pub(crate) fn set_tempo(handle: &mut EmuHandle, tempo: f64) {
+    unsafe { gme_set_tempo(handle.to_raw_mut(), tempo) }
 }
 
-pub(crate) fn mute_voice(handle: &EmuHandle, index: u32, mute: bool) {
-    unsafe { gme_mute_voice(handle.to_raw(), index as i32, mute as i32) }
+pub(crate) fn mute_voice(handle: &mut EmuHandle, index: u32, mute: bool) {
+    unsafe { gme_mute_voice(handle.to_raw_mut(), index as i32, mute as i32) }
 }
 
-pub(crate) fn mute_voices(handle: &EmuHandle, mask: i32) {
-    unsafe { gme_mute_voices(handle.to_raw(), mask) }
+pub(crate) fn mute_voices(handle: &mut EmuHandle, mask: i32) {
+    unsafe { gme_mute_voices(handle.to_raw_mut(), mask) }
 }
 
 pub(crate) fn voice_count(handle: &EmuHandle) -> u32 {
@@ -165,7 +433,7 @@ pub fn type_list() -> Vec<EmuType> {
         while !(*p).is_null() {
             let gme_type = p.read();
             let extension = CStr::from_ptr((*gme_type).extension).to_str().unwrap();
-            types.push(EmuType::from_extension(extension));
+            types.extend(EmuType::from_extension(extension));
             p = p.offset(1);
         }
     }
@@ -184,113 +452,82 @@ fn process_result(result: *const c_char) -> GmeResult<()> {
     }
 }
 
+/// The error Game Music Emu returns for data it doesn't recognize
+fn wrong_file_type() -> GmeError {
+    unsafe {
+        GmeError::new(
+            CStr::from_ptr(gme_wrong_file_type)
+                .to_string_lossy()
+                .into_owned(),
+        )
+    }
+}
+
+fn path_to_cstring(path: &Path) -> GmeResult<CString> {
+    let path = path
+        .to_str()
+        .ok_or_else(|| GmeError::with_kind(GmeErrorKind::InvalidArgument, "Invalid path"))?;
+    CString::new(path).map_err(|e| {
+        GmeError::with_kind(
+            GmeErrorKind::InvalidArgument,
+            format!("Failed to convert path to CString: {}", e),
+        )
+    })
+}
+
 pub(crate) fn get_file_data(path: impl AsRef<Path>) -> std::io::Result<Vec<u8>> {
     std::fs::read(path)
 }
 
-impl From<gme_info_t> for EmuTrackInfo {
-    fn from(info: gme_info_t) -> Self {
-        unsafe {
-            let info_ref = &*info;
-            EmuTrackInfo {
-                length: if info_ref.length >= 0 {
-                    Some(info_ref.length as u32)
-                } else {
-                    None
-                },
-                intro_length: if info_ref.intro_length >= 0 {
-                    Some(info_ref.intro_length as u32)
-                } else {
-                    None
-                },
-                loop_length: if info_ref.loop_length >= 0 {
-                    Some(info_ref.loop_length as u32)
-                } else {
-                    None
-                },
-                play_length: info_ref.play_length as u32,
-                system: if !info_ref.system.is_null() {
-                    Some(
-                        CStr::from_ptr(info_ref.system)
-                            .to_string_lossy()
-                            .into_owned(),
-                    )
-                } else {
-                    None
-                },
-                game: if !info_ref.game.is_null() {
-                    Some(CStr::from_ptr(info_ref.game).to_string_lossy().into_owned())
-                } else {
-                    None
-                },
-                song: if !info_ref.song.is_null() {
-                    Some(CStr::from_ptr(info_ref.song).to_string_lossy().into_owned())
-                } else {
-                    None
-                },
-                author: if !info_ref.author.is_null() {
-                    Some(
-                        CStr::from_ptr(info_ref.author)
-                            .to_string_lossy()
-                            .into_owned(),
-                    )
-                } else {
-                    None
-                },
-                copyright: if !info_ref.copyright.is_null() {
-                    Some(
-                        CStr::from_ptr(info_ref.copyright)
-                            .to_string_lossy()
-                            .into_owned(),
-                    )
-                } else {
-                    None
-                },
-                comment: if !info_ref.comment.is_null() {
-                    Some(
-                        CStr::from_ptr(info_ref.comment)
-                            .to_string_lossy()
-                            .into_owned(),
-                    )
-                } else {
-                    None
-                },
-                dumper: if !info_ref.dumper.is_null() {
-                    Some(
-                        CStr::from_ptr(info_ref.dumper)
-                            .to_string_lossy()
-                            .into_owned(),
-                    )
-                } else {
-                    None
-                },
-            }
+/// # Safety
+/// `info` must point to a valid `gme_info_t_struct` returned by `gme_track_info`
+unsafe fn to_track_info(info: gme_info_t) -> EmuTrackInfo {
+    let info = unsafe { &*info };
+    // gme uses -1 for unknown lengths. A zero total or loop length is also used for "unknown"
+    // or "doesn't loop", so only the intro and fade can be zero.
+    let length = |msec: i32, allow_zero: bool| {
+        (msec > 0 || (allow_zero && msec == 0)).then(|| Duration::from_millis(msec as u64))
+    };
+    let string = |ptr: *const c_char| {
+        if ptr.is_null() {
+            return None;
         }
+        let string = unsafe { CStr::from_ptr(ptr) }.to_string_lossy();
+        (!string.is_empty()).then(|| string.into_owned())
+    };
+    EmuTrackInfo {
+        length: length(info.length, false),
+        intro_length: length(info.intro_length, true),
+        loop_length: length(info.loop_length, false),
+        fade_length: length(info.fade_length, true),
+        play_length: Duration::from_millis(info.play_length.max(0) as u64),
+        system: string(info.system),
+        game: string(info.game),
+        song: string(info.song),
+        author: string(info.author),
+        copyright: string(info.copyright),
+        comment: string(info.comment),
+        dumper: string(info.dumper),
     }
 }
 
-pub(crate) fn load_m3u(handle: &EmuHandle, path: impl AsRef<Path>) -> GmeResult<()> {
-    let cstring = CString::new(
-        path.as_ref()
-            .to_str()
-            .ok_or_else(|| GmeError::new("Invalid path".into()))?,
-    )
-    .map_err(|e| GmeError::new(format!("Failed to convert path to CString: {}", e)))?;
-    unsafe { process_result(gme_load_m3u(handle.to_raw(), cstring.as_ptr())) }
+pub(crate) fn load_m3u(handle: &mut EmuHandle, path: impl AsRef<Path>) -> GmeResult<()> {
+    let cstring = path_to_cstring(path.as_ref())?;
+    unsafe { process_result(gme_load_m3u(handle.to_raw_mut(), cstring.as_ptr())) }
 }
 
-pub(crate) fn load_m3u_data(handle: &EmuHandle, data: &[u8]) -> GmeResult<()> {
+pub(crate) fn load_m3u_data(handle: &mut EmuHandle, data: &[u8]) -> GmeResult<()> {
     unsafe {
         process_result(gme_load_m3u_data(
-            handle.to_raw(),
+            handle.to_raw_mut(),
             data.as_ptr(),
             data.len(),
         ))
     }
 }
 
-pub(crate) fn clear_playlist(handle: &EmuHandle) {
-    unsafe { gme_clear_playlist(handle.to_raw()) }
+pub(crate) fn clear_playlist(handle: &mut EmuHandle) {
+    unsafe { gme_clear_playlist(handle.to_raw_mut()) }
 }
 
 pub(crate) fn track_info(handle: &EmuHandle, track: u32) -> GmeResult<EmuTrackInfo> {
@@ -298,7 +535,7 @@ pub(crate) fn track_info(handle: &EmuHandle, track: u32) -> GmeResult<EmuTrackIn
         let mut info_ptr: gme_info_t = std::ptr::null_mut();
         let err = gme_track_info(handle.to_raw(), &mut info_ptr, track as i32);
         process_result(err)?;
-        let info = EmuTrackInfo::from(info_ptr);
+        let info = to_track_info(info_ptr);
         free_info(info_ptr);
         Ok(info)
     }
@@ -329,27 +566,38 @@ pub(crate) fn equalizer(handle: &EmuHandle) -> EmuEqualizer {
     EmuEqualizer::from(gme_eq)
 }
 
-pub(crate) fn set_equalizer(handle: &EmuHandle, eq: EmuEqualizer) -> GmeResult<()> {
+pub(crate) fn set_equalizer(handle: &mut EmuHandle, eq: EmuEqualizer) -> GmeResult<()> {
+    eq.validate()?;
+    let [d2, d3, d4, d5, d6, d7, d8, d9] = eq.reserved;
     let gme_eq = gme_equalizer_t {
         treble: eq.treble,
         bass: eq.bass,
-        d2: 0.0,
-        d3: 0.0,
-        d4: 0.0,
-        d5: 0.0,
-        d6: 0.0,
-        d7: 0.0,
-        d8: 0.0,
-        d9: 0.0,
+        d2,
+        d3,
+        d4,
+        d5,
+        d6,
+        d7,
+        d8,
+        d9,
     };
     unsafe {
-        gme_set_equalizer(handle.to_raw(), &gme_eq);
+        gme_set_equalizer(handle.to_raw_mut(), &gme_eq);
     }
     Ok(())
 }
 
-pub(crate) fn enable_accuracy(handle: &EmuHandle, enable: bool) {
-    unsafe { gme_enable_accuracy(handle.to_raw(), enable as i32) }
+pub(crate) fn enable_accuracy(handle: &mut EmuHandle, enable: bool) {
+    unsafe { gme_enable_accuracy(handle.to_raw_mut(), enable as i32) }
+}
+
+pub(crate) fn disable_echo(handle: &mut EmuHandle, disable: bool) {
+    unsafe { gme_disable_echo(handle.to_raw_mut(), disable as i32) }
+}
+
+#[cfg(feature = "spc")]
+pub(crate) fn spc_disable_surround(handle: &mut EmuHandle, disable: bool) {
+    unsafe { gme_spc_disable_surround(handle.to_raw_mut(), disable as i32) }
 }
 
 impl From<gme_equalizer_t> for EmuEqualizer {
@@ -357,14 +605,18 @@ impl From<gme_equalizer_t> for EmuEqualizer {
         Self {
             treble: gme_eq.treble,
             bass: gme_eq.bass,
+            reserved: [
+                gme_eq.d2, gme_eq.d3, gme_eq.d4, gme_eq.d5, gme_eq.d6, gme_eq.d7, gme_eq.d8,
+                gme_eq.d9,
+            ],
         }
     }
 }
 
+/// Opaque `Music_Emu` from the C++ code. Only ever used behind a pointer.
 #[repr(C)]
-#[derive(Clone)]
 pub(crate) struct MusicEmu {
-    _private: isize,
+    _private: [u8; 0],
 }
 
 // gme_type_t_ is struct
@@ -396,7 +648,7 @@ pub(crate) struct gme_info_t_struct {
     pub loop_length: i32,
     pub play_length: i32,
     /* reserved */
-    pub i4: i32,
+    pub fade_length: i32,
     pub i5: i32,
     pub i6: i32,
     pub i7: i32,
@@ -431,6 +683,10 @@ pub(crate) struct gme_info_t_struct {
 #[allow(non_camel_case_types)]
 type gme_info_t = *mut gme_info_t_struct;
 
+#[allow(non_camel_case_types)]
+type gme_reader_t =
+    unsafe extern "C" fn(your_data: *mut c_void, out: *mut c_void, count: i32) -> *const c_char;
+
 #[repr(C)]
 pub(crate) struct gme_equalizer_t_struct {
     pub treble: f64,
@@ -451,7 +707,7 @@ type gme_equalizer_t = gme_equalizer_t_struct;
 
 unsafe extern "C" {
     /// Finish using emulator and free memory
-    fn gme_delete(emu: *const MusicEmu);
+    fn gme_delete(emu: *mut MusicEmu);
 
     /// Determine likely game music type based on first four bytes of file. Returns string
     /// containing proper file suffix (i.e. "NSF", "SPC", etc.) or "" if file header is not
@@ -459,55 +715,122 @@ unsafe extern "C" {
     fn gme_identify_header(header: *const u8) -> *const c_char;
 
     /// Get corresponding music type for file path or extension passed in.
-    fn gme_identify_extension(extension: *const c_char) -> *const gme_type_t;
+    fn gme_identify_extension(extension: *const c_char) -> gme_type_t;
+
+    /// Determine file type based on file's extension or header (if extension isn't recognized).
+    /// Sets `type_out` to type, or null if unrecognized or error.
+    fn gme_identify_file(path: *const c_char, type_out: *mut gme_type_t) -> *const c_char;
+
+    /// Get typical file extension for a given music type
+    fn gme_type_extension(music_type: gme_type_t) -> *const c_char;
+
+    /// Type of this emulator
+    fn gme_type(emu: *const MusicEmu) -> gme_type_t;
+
+    /// Name of game system for this music file type
+    fn gme_type_system(music_type: gme_type_t) -> *const c_char;
+
+    /// True if this music file type supports multiple tracks
+    fn gme_type_multitrack(music_type: gme_type_t) -> i32;
+
+    /// Return the fixed track count of an emu file type
+    fn gme_fixed_track_count(music_type: gme_type_t) -> i32;
+
+    /// Error returned if file type is not supported
+    static gme_wrong_file_type: *const c_char;
 
     /// Load music file from memory into emulator. Makes a copy of data passed.
-    fn gme_load_data(emu: *const MusicEmu, data: *const u8, size: usize) -> *const c_char;
+    fn gme_load_data(emu: *mut MusicEmu, data: *const u8, size: usize) -> *const c_char;
+
+    /// Load multiple single-track music files from memory into emulator. `data` holds the files
+    /// back to back, and `sizes` holds the size of each.
+    fn gme_load_tracks(
+        emu: *mut MusicEmu,
+        data: *const u8,
+        sizes: *mut c_long,
+        count: i32,
+    ) -> *const c_char;
+
+    /// Load music file using custom data reader function that will be called to read file data.
+    /// Most emulators load the entire file in one read call.
+    fn gme_load_custom(
+        emu: *mut MusicEmu,
+        reader: gme_reader_t,
+        file_size: c_long,
+        your_data: *mut c_void,
+    ) -> *const c_char;
 
     /// Generate `count` 16-bit signed samples into `buffer`. Output is in stereo.
-    fn gme_play(emu: *const MusicEmu, count: i32, out: *mut i16) -> *const c_char;
+    fn gme_play(emu: *mut MusicEmu, count: i32, out: *mut i16) -> *const c_char;
 
     /// Create new emulator and set sample rate.
-    fn gme_new_emu(gme_type: *const gme_type_t, sample_rate: i32) -> *const MusicEmu;
+    fn gme_new_emu(gme_type: gme_type_t, sample_rate: i32) -> *mut MusicEmu;
+
+    /// Create new multichannel emulator and set sample rate. Types without multichannel support
+    /// fall back to stereo.
+    fn gme_new_emu_multi_channel(gme_type: gme_type_t, sample_rate: i32) -> *mut MusicEmu;
+
+    /// True if all 8 voices are rendered to their own stereo channel
+    fn gme_multi_channel(emu: *const MusicEmu) -> i32;
 
     /// Start a track, where 0 is the first track
-    fn gme_start_track(emu: *const MusicEmu, index: i32) -> *const c_char;
+    fn gme_start_track(emu: *mut MusicEmu, index: i32) -> *const c_char;
+
+    /// Most recent warning string, or NULL if none. Clears current warning after returning.
+    /// Warning is also cleared when loading a file and starting a track.
+    fn gme_warning(emu: *mut MusicEmu) -> *const c_char;
 
     /// Number of milliseconds played since beginning of track
     fn gme_tell(emu: *const MusicEmu) -> i32;
 
+    /// Number of samples generated since beginning of track
+    fn gme_tell_samples(emu: *const MusicEmu) -> i32;
+
+    /// Number of milliseconds played since beginning of track (scaled with tempo)
+    fn gme_tell_scaled(emu: *const MusicEmu) -> i32;
+
     /// Number of tracks available
     fn gme_track_count(emu: *const MusicEmu) -> i32;
 
     /// True if a track has reached its end
-    fn gme_track_ended(emu: *const MusicEmu) -> bool;
+    fn gme_track_ended(emu: *const MusicEmu) -> i32;
 
     /// Pointer to array of all music types, with NULL entry at end.
     fn gme_type_list() -> *const gme_type_t;
 
     /// Seek to specified position in milliseconds
-    fn gme_seek(emu: *const MusicEmu, msec: i32) -> *const c_char;
+    fn gme_seek(emu: *mut MusicEmu, msec: i32) -> *const c_char;
+
+    /// Equivalent to restarting track then skipping n samples
+    fn gme_seek_samples(emu: *mut MusicEmu, n: i32) -> *const c_char;
+
+    /// Seek to new time in track (scaled with tempo)
+    fn gme_seek_scaled(emu: *mut MusicEmu, msec: i32) -> *const c_char;
+
+    /// Set time to start fading track out, and how long the fade lasts
+    fn gme_set_fade_msecs(emu: *mut MusicEmu, start_msec: i32, length_msecs: i32);
 
-    /// Set fade parameters
-    fn gme_set_fade(emu: *const MusicEmu, start_msec: i32);
+    /// If `do_autoload_limit` is nonzero, then automatically load track length metadata (if
+    /// present) and terminate playback once the track length has been reached
+    fn gme_set_autoload_playback_limit(emu: *mut MusicEmu, do_autoload_limit: i32);
 
     /// Adjust stereo echo depth, where 0.0 = off and 1.0 = maximum. Has no effect for
     /// GYM, SPC, and Sega Genesis VGM music
-    fn gme_set_stereo_depth(emu: *const MusicEmu, depth: f64);
+    fn gme_set_stereo_depth(emu: *mut MusicEmu, depth: f64);
 
     /// Disable automatic end-of-track detection and skipping of silence at beginning
     /// if ignore is true
-    fn gme_ignore_silence(emu: *const MusicEmu, ignore: i32);
+    fn gme_ignore_silence(emu: *mut MusicEmu, ignore: i32);
 
     /// Set tempo multiplier (1.0 = normal speed)
-    fn gme_set_tempo(emu: *const MusicEmu, tempo: f64);
+    fn gme_set_tempo(emu: *mut MusicEmu, tempo: f64);
 
     /// Mute or unmute a specific voice
-    fn gme_mute_voice(emu: *const MusicEmu, index: i32, mute: i32);
+    fn gme_mute_voice(emu: *mut MusicEmu, index: i32, mute: i32);
 
     /// Set muting state of all voices at once using a bit mask, where -1 mutes all
     /// voices, 0 unmutes them all, 0x01 mutes just the first voice, etc.
-    fn gme_mute_voices(emu: *const MusicEmu, mask: i32);
+    fn gme_mute_voices(emu: *mut MusicEmu, mask: i32);
 
     /// Get number of voices available in current track
     fn gme_voice_count(emu: *const MusicEmu) -> i32;
@@ -516,13 +839,13 @@ unsafe extern "C" {
     fn gme_voice_name(emu: *const MusicEmu, index: i32) -> *const c_char;
 
     /// Load M3U playlist file
-    fn gme_load_m3u(emu: *const MusicEmu, path: *const c_char) -> *const c_char;
+    fn gme_load_m3u(emu: *mut MusicEmu, path: *const c_char) -> *const c_char;
 
     /// Load M3U playlist data from memory
-    fn gme_load_m3u_data(emu: *const MusicEmu, data: *const u8, size: usize) -> *const c_char;
+    fn gme_load_m3u_data(emu: *mut MusicEmu, data: *const u8, size: usize) -> *const c_char;
 
     /// Clear loaded playlist
-    fn gme_clear_playlist(emu: *const MusicEmu);
+    fn gme_clear_playlist(emu: *mut MusicEmu);
 
     /// Get track info for specified track
     fn gme_track_info(emu: *const MusicEmu, out: *mut gme_info_t, track: i32) -> *const c_char;
@@ -534,10 +857,18 @@ unsafe extern "C" {
     fn gme_equalizer(emu: *const MusicEmu, out: *mut gme_equalizer_t);
 
     /// Set equalizer settings
-    fn gme_set_equalizer(emu: *const MusicEmu, eq: *const gme_equalizer_t);
+    fn gme_set_equalizer(emu: *mut MusicEmu, eq: *const gme_equalizer_t);
 
     /// Enable or disable high-accuracy emulation mode
-    fn gme_enable_accuracy(emu: *const MusicEmu, enable: i32);
+    fn gme_enable_accuracy(emu: *mut MusicEmu, enable: i32);
+
+    /// Disable echo effect for SPC files
+    fn gme_disable_echo(emu: *mut MusicEmu, disable: i32);
+
+    /// Prevent channels and global volumes from being phase-negated. Only affects SPC files.
+    /// Defined in `src/shim/spc_shim.cpp`.
+    #[cfg(feature = "spc")]
+    fn gme_spc_disable_surround(emu: *mut MusicEmu, disable: i32);
 }
 
 #[cfg(test)]
@@ -551,46 +882,92 @@ mod tests {
         assert!(types.len() > 1);
     }
 
+    #[test]
+    fn test_identify_header() {
+        let data = get_test_nsf_data();
+        assert_eq!(identify_header(&data), Some(EmuType::Nsf));
+        assert_eq!(identify_header(&data[..3]), None);
+        assert_eq!(identify_header(&[0, 1, 2, 3, 4]), None);
+    }
+
+    #[test]
+    fn test_identify_extension() {
+        assert_eq!(identify_extension("NSF"), Some(EmuType::Nsf));
+        assert_eq!(identify_extension("music/song.nsf"), Some(EmuType::Nsf));
+        assert_eq!(identify_extension("song.mp3"), None);
+    }
+
+    #[test]
+    fn test_identify_file() {
+        assert_eq!(identify_file(TEST_NSF_PATH).unwrap(), Some(EmuType::Nsf));
+        assert_eq!(identify_file(TEST_M3U_PATH).unwrap(), None);
+    }
+
+    #[test]
+    fn test_type_metadata() {
+        assert_eq!(type_system(EmuType::Nsf), Some("Nintendo NES"));
+        assert_eq!(type_multitrack(EmuType::Nsf), Some(true));
+        assert_eq!(type_multitrack(EmuType::Spc), Some(false));
+        assert_eq!(fixed_track_count(EmuType::Nsf), Some(0));
+        assert_eq!(fixed_track_count(EmuType::Spc), Some(1));
+        assert_eq!(fixed_track_count(EmuType::Hes), Some(256));
+    }
+
+    #[test]
+    fn test_open_unrecognized_data() {
+        let err = open_data(&[1, 2, 3], 44100).err().unwrap();
+        assert_eq!(err.message(), "Wrong file type for this emulator");
+    }
+
     #[test]
     fn test_open_data() {
-        let handle = open_data(&get_test_nsf_data(), 44100).unwrap();
+        let mut handle = open_data(&get_test_nsf_data(), 44100).unwrap();
         assert_eq!(track_count(&handle), 1);
         assert_eq!(voice_count(&handle), 5);
         assert_eq!(voice_name(&handle, 0).as_deref(), Some("Square 1"));
-        start_track(&handle, 0).unwrap();
+        start_track(&mut handle, 0).unwrap();
     }
 
     #[test]
     fn test_open_file() {
-        let handle = open_file(TEST_NSF_PATH, 44100).unwrap();
+        let mut handle = open_file(TEST_NSF_PATH, 44100).unwrap();
         assert_eq!(track_count(&handle), 1);
         assert_eq!(voice_count(&handle), 5);
         assert_eq!(voice_name(&handle, 0).as_deref(), Some("Square 1"));
-        start_track(&handle, 0).unwrap();
+        start_track(&mut handle, 0).unwrap();
     }
 
     #[test]
     fn test_seek_and_tell() {
-        let handle = open_data(&get_test_nsf_data(), 44100).unwrap();
-        start_track(&handle, 0).unwrap();
-        seek(&handle, 10000).unwrap();
+        let mut handle = open_data(&get_test_nsf_data(), 44100).unwrap();
+        start_track(&mut handle, 0).unwrap();
+        seek(&mut handle, 10000).unwrap();
         assert!(tell(&handle) >= 10000);
     }
 
+    #[test]
+    fn test_seek_and_tell_samples() {
+        let mut handle = open_data(&get_test_nsf_data(), 44100).unwrap();
+        start_track(&mut handle, 0).unwrap();
+        seek_samples(&mut handle, 88200).unwrap();
+        assert_eq!(tell_samples(&handle), 88200);
+        assert_eq!(tell(&handle), 1000);
+    }
+
     #[test]
     fn test_open_m3u_data() {
-        let handle = open_file(TEST_NSF_PATH, 44100).unwrap();
+        let mut handle = open_file(TEST_NSF_PATH, 44100).unwrap();
         assert_eq!(track_count(&handle), 1);
-        load_m3u_data(&handle, &get_test_m3u_data()).unwrap();
-        clear_playlist(&handle);
+        load_m3u_data(&mut handle, &get_test_m3u_data()).unwrap();
+        clear_playlist(&mut handle);
     }
 
     #[test]
     fn test_open_m3u_file() {
-        let handle = open_file(TEST_NSF_PATH, 44100).unwrap();
+        let mut handle = open_file(TEST_NSF_PATH, 44100).unwrap();
         assert_eq!(track_count(&handle), 1);
-        load_m3u(&handle, TEST_M3U_PATH).unwrap();
-        clear_playlist(&handle);
+        load_m3u(&mut handle, TEST_M3U_PATH).unwrap();
+        clear_playlist(&mut handle);
     }
 
     #[test]
@@ -629,12 +1006,12 @@ mod tests {
 
     #[test]
     fn test_equalizer() {
-        let handle = open_file(TEST_NSF_PATH, 44100).unwrap();
+        let mut handle = open_file(TEST_NSF_PATH, 44100).unwrap();
         let eq1 = equalizer(&handle);
         assert_eq!(eq1.bass, 80.0);
         let mut eq2 = eq1.clone();
         eq2.bass = 30.0;
-        set_equalizer(&handle, eq2).unwrap();
+        set_equalizer(&mut handle, eq2).unwrap();
         let eq3 = equalizer(&handle);
         assert_eq!(eq3.bass, 30.0);
     }
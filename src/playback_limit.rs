@@ -0,0 +1,69 @@
+use crate::emu_track_info::EmuTrackInfo;
+use std::time::Duration;
+
+/// Controls how long a track plays before it fades out and [crate::GameMusicEmu::track_ended]
+/// returns true
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PlaybackLimit {
+    /// Let Game Music Emu load the length from the file's metadata, for the formats that
+    /// support it. Other tracks play until a long stretch of silence.
+    #[default]
+    Auto,
+    /// Start fading out at a fixed time
+    Fixed(Duration),
+    /// Play the intro, then the looping section this many times, then fade out. Tracks without
+    /// loop information fade out at their play length instead.
+    Loops(u32),
+    /// Play forever. Fading and the end-of-track silence detection are turned off.
+    Infinite,
+}
+
+impl PlaybackLimit {
+    /// When a track with `info` should start fading out. Returns `None` if this limit doesn't
+    /// fade the track out itself.
+    pub fn fade_start(&self, info: &EmuTrackInfo) -> Option<Duration> {
+        match *self {
+            PlaybackLimit::Auto | PlaybackLimit::Infinite => None,
+            PlaybackLimit::Fixed(start) => Some(start),
+            PlaybackLimit::Loops(count) => Some(info.fade_start(count)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fade_start() {
+        let info = EmuTrackInfo {
+            intro_length: Some(Duration::from_secs(5)),
+            loop_length: Some(Duration::from_secs(20)),
+            play_length: Duration::from_secs(45),
+            ..EmuTrackInfo::default()
+        };
+        assert_eq!(PlaybackLimit::Auto.fade_start(&info), None);
+        assert_eq!(PlaybackLimit::Infinite.fade_start(&info), None);
+        assert_eq!(
+            PlaybackLimit::Fixed(Duration::from_secs(3)).fade_start(&info),
+            Some(Duration::from_secs(3))
+        );
+        assert_eq!(
+            PlaybackLimit::Loops(3).fade_start(&info),
+            Some(Duration::from_secs(65))
+        );
+    }
+
+    #[test]
+    fn test_loops_without_loop_info() {
+        let info = EmuTrackInfo {
+            play_length: Duration::from_secs(150),
+            ..EmuTrackInfo::default()
+        };
+        assert_eq!(
+            PlaybackLimit::Loops(2).fade_start(&info),
+            Some(Duration::from_secs(150))
+        );
+    }
+}
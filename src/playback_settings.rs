@@ -0,0 +1,115 @@
+use crate::GmeResult;
+use crate::emu_equalizer::EmuEqualizer;
+use crate::playback_limit::PlaybackLimit;
+use crate::voice_mask::VoiceMask;
+use crate::wrapper::{DEFAULT_FADE_LENGTH, GameMusicEmu};
+use std::time::Duration;
+
+/// Everything a [GameMusicEmu] can be configured with, so it can be stored and applied in one
+/// call. With the `serde` feature, missing fields deserialize to their defaults.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(default)
+)]
+pub struct PlaybackSettings {
+    /// Playback speed, where 1.0 is normal
+    pub tempo: f64,
+    /// Stereo echo depth, where 0.0 is off and 1.0 is maximum
+    pub stereo_depth: f64,
+    /// `None` keeps the emulator's own equalizer
+    pub equalizer: Option<EmuEqualizer>,
+    pub muted_voices: VoiceMask,
+    pub ignore_silence: bool,
+    pub accuracy: bool,
+    pub disable_echo: bool,
+    pub playback_limit: PlaybackLimit,
+    /// Length of the fade out at the end of the [PlaybackLimit]
+    pub fade_length: Duration,
+    /// Length of the fade in at the start of a track and after seeking
+    pub fade_in: Duration,
+    /// Gain applied by [GameMusicEmu::play_f32]
+    pub gain: f32,
+    /// Soft clipping in [GameMusicEmu::play_f32]
+    pub soft_clip: bool,
+}
+
+impl Default for PlaybackSettings {
+    fn default() -> Self {
+        Self {
+            tempo: 1.0,
+            stereo_depth: 0.0,
+            equalizer: None,
+            muted_voices: VoiceMask::empty(),
+            ignore_silence: false,
+            accuracy: false,
+            disable_echo: false,
+            playback_limit: PlaybackLimit::default(),
+            fade_length: DEFAULT_FADE_LENGTH,
+            fade_in: Duration::ZERO,
+            gain: 1.0,
+            soft_clip: false,
+        }
+    }
+}
+
+impl PlaybackSettings {
+    /// Apply every setting to `emu`. The playback limit and fade apply to the current track
+    /// right away.
+    pub fn apply(&self, emu: &mut GameMusicEmu) -> GmeResult<()> {
+        emu.set_tempo(self.tempo);
+        emu.set_stereo_depth(self.stereo_depth);
+        if let Some(equalizer) = &self.equalizer {
+            emu.set_equalizer(equalizer.clone())?;
+        }
+        emu.mute_voices(self.muted_voices);
+        emu.ignore_silence(self.ignore_silence);
+        emu.enable_accuracy(self.accuracy);
+        emu.disable_echo(self.disable_echo);
+        emu.set_fade_in(self.fade_in);
+        emu.set_playback_fade_length(self.fade_length);
+        emu.set_playback_limit(self.playback_limit);
+        emu.set_gain(self.gain);
+        emu.set_soft_clip(self.soft_clip);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_apply() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        let settings = PlaybackSettings {
+            equalizer: Some(EmuEqualizer::new(-14.0, 80.0)),
+            playback_limit: PlaybackLimit::Fixed(Duration::from_secs(1)),
+            fade_length: Duration::from_secs(2),
+            ..PlaybackSettings::default()
+        };
+        settings.apply(&mut gme).unwrap();
+        assert_eq!(gme.equalizer(), EmuEqualizer::new(-14.0, 80.0));
+        assert_eq!(gme.playback_limit(), settings.playback_limit);
+        assert_eq!(gme.playback_fade_length(), Duration::from_secs(2));
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn test_serde() {
+        let settings = PlaybackSettings {
+            tempo: 1.5,
+            playback_limit: PlaybackLimit::Loops(2),
+            ..PlaybackSettings::default()
+        };
+        let json = serde_json::to_string(&settings).unwrap();
+        let round_trip: PlaybackSettings = serde_json::from_str(&json).unwrap();
+        assert_eq!(round_trip, settings);
+
+        let partial: PlaybackSettings = serde_json::from_str(r#"{"tempo": 2.0}"#).unwrap();
+        assert_eq!(partial.tempo, 2.0);
+        assert_eq!(partial.fade_length, DEFAULT_FADE_LENGTH);
+    }
+}
@@ -0,0 +1,562 @@
+//! Playback of a [GameMusicEmu] on an audio output device with cpal
+
+use crate::error::{GmeError, GmeOrIoError, GmeResult};
+use crate::wrapper::GameMusicEmu;
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, StreamConfig};
+use rtrb::{Consumer, Producer, PushError, RingBuffer};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::thread::{self, Thread};
+use std::time::Duration;
+
+/// Commands and events that can be waiting at once. Sending a command fails when the audio
+/// thread falls this far behind, and events are dropped when they aren't received.
+const QUEUE_LEN: usize = 64;
+
+/// Samples rendered at a time, in a buffer on the stack
+const CHUNK_LEN: usize = 2048;
+
+/// How often [Player::wait_event] checks for events
+const EVENT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// How often the worker thread checks whether the player was dropped
+const WORKER_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Error creating or controlling a [Player]
+#[derive(Debug, thiserror::Error)]
+pub enum PlayerError {
+    #[error("No audio output device is available")]
+    NoDevice,
+    #[error("Unsupported sample format: {0}")]
+    UnsupportedFormat(SampleFormat),
+    #[error(transparent)]
+    Load(#[from] GmeOrIoError),
+    #[error(transparent)]
+    DefaultConfig(#[from] cpal::DefaultStreamConfigError),
+    #[error(transparent)]
+    BuildStream(#[from] cpal::BuildStreamError),
+    #[error(transparent)]
+    PlayStream(#[from] cpal::PlayStreamError),
+    /// The audio thread hasn't received the commands that were already sent
+    #[error("Too many commands are waiting for the audio thread")]
+    QueueFull,
+}
+
+impl From<GmeError> for PlayerError {
+    fn from(err: GmeError) -> Self {
+        PlayerError::Load(err.into())
+    }
+}
+
+/// Something that happened on the audio thread, received with [Player::try_event]
+#[derive(Debug)]
+pub enum PlayerEvent {
+    /// A track started, by [Player::start_track] or a skip
+    TrackStarted(usize),
+    /// The track reached the end of its [crate::PlaybackLimit]. Output is silent until another
+    /// track is started.
+    TrackEnded(usize),
+    /// The emulator failed while starting, seeking or playing. Playback is paused.
+    Error(GmeError),
+    /// The audio device reported an error
+    StreamError(cpal::StreamError),
+}
+
+#[derive(Debug)]
+enum Command {
+    Play,
+    Pause,
+    Stop,
+    StartTrack(usize),
+    NextTrack,
+    PreviousTrack,
+    Seek(Duration),
+}
+
+/// Work that is too slow for the audio thread, since the emulator has to run ahead
+#[derive(Debug)]
+enum Task {
+    StartTrack(usize),
+    Seek(Duration),
+}
+
+/// The emulator, handed to the worker thread for a [Task] and back with its result
+struct Job {
+    emu: GameMusicEmu,
+    task: Task,
+    result: GmeResult<()>,
+}
+
+/// Plays a [GameMusicEmu] on an audio output device. Devices with `i16`, `u16` and `f32`
+/// samples are supported.
+///
+/// The emulator plays on the device's audio thread. Controls reach it through a lock-free
+/// queue, and fail with [PlayerError::QueueFull] instead of blocking when it is full. It
+/// reports back through [PlayerEvent]s. Starting tracks and seeking run the emulator ahead
+/// without output, which is too slow for the audio thread, so they happen on a worker thread
+/// while the output is silent. Commands sent after them wait until they are done.
+///
+/// A player starts paused on the first track. Dropping it stops the output.
+pub struct Player {
+    _stream: cpal::Stream,
+    commands: Producer<Command>,
+    events: Consumer<PlayerEvent>,
+    /// Errors from the audio device, which reports them on its own thread
+    stream_errors: Consumer<PlayerEvent>,
+    shared: Arc<Shared>,
+    sample_rate: u32,
+    track_count: usize,
+}
+
+/// State the audio thread publishes for [Player]
+struct Shared {
+    /// [f32] bits
+    volume: AtomicU32,
+    /// Milliseconds since the start of the track
+    position: AtomicU32,
+}
+
+impl Player {
+    /// Play the file at `path` on the default output device
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, PlayerError> {
+        Self::new(|sample_rate| GameMusicEmu::from_file(path, sample_rate))
+    }
+
+    /// Play on the default output device. See [Self::with_device].
+    pub fn new(
+        load: impl FnOnce(u32) -> Result<GameMusicEmu, GmeOrIoError>,
+    ) -> Result<Self, PlayerError> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or(PlayerError::NoDevice)?;
+        Self::with_device(&device, load)
+    }
+
+    /// Play on `device`. `load` receives the device's sample rate and returns the emulator to
+    /// play, with its settings applied.
+    pub fn with_device(
+        device: &cpal::Device,
+        load: impl FnOnce(u32) -> Result<GameMusicEmu, GmeOrIoError>,
+    ) -> Result<Self, PlayerError> {
+        let default_config = device.default_output_config()?;
+        let config = StreamConfig {
+            channels: 2,
+            sample_rate: default_config.sample_rate(),
+            buffer_size: cpal::BufferSize::Default,
+        };
+        let sample_rate = config.sample_rate.0;
+        let mut emu = load(sample_rate)?;
+        emu.start_track(0)?;
+        let track_count = emu.track_count();
+
+        let (commands, command_receiver) = RingBuffer::new(QUEUE_LEN);
+        let (event_sender, events) = RingBuffer::new(QUEUE_LEN);
+        let (stream_error_sender, stream_errors) = RingBuffer::new(QUEUE_LEN);
+        let shared = Arc::new(Shared {
+            volume: AtomicU32::new(1.0_f32.to_bits()),
+            position: AtomicU32::new(0),
+        });
+        let engine = Engine::new(emu, command_receiver, event_sender, shared.clone());
+        let stream = match default_config.sample_format() {
+            SampleFormat::I16 => build_stream::<i16>(device, &config, engine, stream_error_sender),
+            SampleFormat::U16 => build_stream::<u16>(device, &config, engine, stream_error_sender),
+            SampleFormat::F32 => build_stream::<f32>(device, &config, engine, stream_error_sender),
+            format => return Err(PlayerError::UnsupportedFormat(format)),
+        }?;
+        stream.play()?;
+
+        Ok(Self {
+            _stream: stream,
+            commands,
+            events,
+            stream_errors,
+            shared,
+            sample_rate,
+            track_count,
+        })
+    }
+
+    pub fn play(&mut self) -> Result<(), PlayerError> {
+        self.send(Command::Play)
+    }
+
+    pub fn pause(&mut self) -> Result<(), PlayerError> {
+        self.send(Command::Pause)
+    }
+
+    /// Pause and go back to the start of the current track
+    pub fn stop(&mut self) -> Result<(), PlayerError> {
+        self.send(Command::Stop)
+    }
+
+    /// Start a track, where 0 is the first track. Doesn't change whether the player is paused.
+    pub fn start_track(&mut self, index: usize) -> Result<(), PlayerError> {
+        self.send(Command::StartTrack(index))
+    }
+
+    /// Start the next track. Does nothing on the last track.
+    pub fn next_track(&mut self) -> Result<(), PlayerError> {
+        self.send(Command::NextTrack)
+    }
+
+    /// Start the previous track. Does nothing on the first track.
+    pub fn previous_track(&mut self) -> Result<(), PlayerError> {
+        self.send(Command::PreviousTrack)
+    }
+
+    /// Seek in the current track. Seeking far forward or backwards takes a while, and the
+    /// output is silent until it is done.
+    pub fn seek(&mut self, position: Duration) -> Result<(), PlayerError> {
+        self.send(Command::Seek(position))
+    }
+
+    /// Set the output volume, where 1.0 is the emulator's own level, including any
+    /// [GameMusicEmu::set_gain]. Applies right away.
+    pub fn set_volume(&self, volume: f32) {
+        self.shared
+            .volume
+            .store(volume.to_bits(), Ordering::Relaxed);
+    }
+
+    pub fn volume(&self) -> f32 {
+        f32::from_bits(self.shared.volume.load(Ordering::Relaxed))
+    }
+
+    /// Time played since the start of the current track, as of the last audio callback
+    pub fn position(&self) -> Duration {
+        Duration::from_millis(self.shared.position.load(Ordering::Relaxed) as u64)
+    }
+
+    /// Sample rate of the output device, which the emulator runs at
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    pub fn track_count(&self) -> usize {
+        self.track_count
+    }
+
+    /// The next event from the audio thread, or `None` if there is none
+    pub fn try_event(&mut self) -> Option<PlayerEvent> {
+        self.events.pop().or_else(|_| self.stream_errors.pop()).ok()
+    }
+
+    /// Wait for the next event from the audio thread. Events are checked for every few
+    /// milliseconds, since the audio thread can't wake a waiting thread without locking.
+    pub fn wait_event(&mut self) -> Option<PlayerEvent> {
+        loop {
+            if let Some(event) = self.try_event() {
+                return Some(event);
+            }
+            if self.events.is_abandoned() {
+                return None;
+            }
+            thread::sleep(EVENT_POLL_INTERVAL);
+        }
+    }
+
+    fn send(&mut self, command: Command) -> Result<(), PlayerError> {
+        self.commands
+            .push(command)
+            .map_err(|_| PlayerError::QueueFull)
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &StreamConfig,
+    mut engine: Engine,
+    mut stream_errors: Producer<PlayerEvent>,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    device.build_output_stream(
+        config,
+        move |output: &mut [T], _: &cpal::OutputCallbackInfo| engine.render(output),
+        move |err| {
+            let _ = stream_errors.push(PlayerEvent::StreamError(err));
+        },
+        None,
+    )
+}
+
+/// The part of a [Player] that runs on the audio thread. It doesn't lock or block, and only
+/// allocates when the emulator reports an error or warning. [Task]s are handed to the worker
+/// thread.
+struct Engine {
+    /// `None` while the worker thread has it
+    emu: Option<GameMusicEmu>,
+    playing: bool,
+    /// The current track ended, so it isn't played until another one starts
+    ended: bool,
+    commands: Consumer<Command>,
+    events: Producer<PlayerEvent>,
+    jobs: Producer<Job>,
+    finished_jobs: Consumer<Job>,
+    worker: Thread,
+    shared: Arc<Shared>,
+}
+
+impl Engine {
+    /// Also starts the worker thread, which stops when the engine is dropped
+    fn new(
+        emu: GameMusicEmu,
+        commands: Consumer<Command>,
+        events: Producer<PlayerEvent>,
+        shared: Arc<Shared>,
+    ) -> Self {
+        // There is only one emulator, so only one job can be waiting
+        let (jobs, job_receiver) = RingBuffer::new(1);
+        let (finished_sender, finished_jobs) = RingBuffer::new(1);
+        let worker = thread::spawn(move || run_worker(job_receiver, finished_sender));
+        Self {
+            emu: Some(emu),
+            playing: false,
+            ended: false,
+            commands,
+            events,
+            jobs,
+            finished_jobs,
+            worker: worker.thread().clone(),
+            shared,
+        }
+    }
+
+    fn render<T: SizedSample + FromSample<f32>>(&mut self, output: &mut [T]) {
+        self.receive_finished_job();
+        self.handle_commands();
+        let volume = f32::from_bits(self.shared.volume.load(Ordering::Relaxed));
+
+        let mut samples = [0.0_f32; CHUNK_LEN];
+        for chunk in output.chunks_mut(CHUNK_LEN) {
+            let samples = &mut samples[..chunk.len()];
+            match &mut self.emu {
+                Some(emu) if self.playing && !self.ended => {
+                    if let Err(err) = emu.play_f32(samples) {
+                        self.playing = false;
+                        samples.fill(0.0);
+                        let _ = self.events.push(PlayerEvent::Error(err));
+                    } else if emu.track_ended() {
+                        self.ended = true;
+                        if let Some(track) = emu.current_track() {
+                            let _ = self.events.push(PlayerEvent::TrackEnded(track));
+                        }
+                    }
+                }
+                _ => samples.fill(0.0),
+            }
+            for (out, &sample) in chunk.iter_mut().zip(samples.iter()) {
+                *out = T::from_sample(sample * volume);
+            }
+        }
+        if let Some(emu) = &self.emu {
+            let position = emu.tell().as_millis().min(u32::MAX as u128) as u32;
+            self.shared.position.store(position, Ordering::Relaxed);
+        }
+    }
+
+    fn handle_commands(&mut self) {
+        // Commands after a task wait until the worker thread returns the emulator
+        while let Some(emu) = &self.emu {
+            let Ok(command) = self.commands.pop() else {
+                break;
+            };
+            let current = emu.current_track().unwrap_or(0);
+            let track_count = emu.track_count();
+            match command {
+                Command::Play => self.playing = true,
+                Command::Pause => self.playing = false,
+                Command::Stop => {
+                    self.playing = false;
+                    self.start_task(Task::StartTrack(current));
+                }
+                Command::StartTrack(index) => self.start_task(Task::StartTrack(index)),
+                Command::NextTrack => {
+                    if current + 1 < track_count {
+                        self.start_task(Task::StartTrack(current + 1));
+                    }
+                }
+                Command::PreviousTrack => {
+                    if current > 0 {
+                        self.start_task(Task::StartTrack(current - 1));
+                    }
+                }
+                Command::Seek(position) => self.start_task(Task::Seek(position)),
+            }
+        }
+    }
+
+    /// Hand the emulator to the worker thread
+    fn start_task(&mut self, task: Task) {
+        let Some(emu) = self.emu.take() else {
+            return;
+        };
+        let job = Job {
+            emu,
+            task,
+            result: Ok(()),
+        };
+        match self.jobs.push(job) {
+            Ok(()) => self.worker.unpark(),
+            Err(PushError::Full(job)) => self.emu = Some(job.emu),
+        }
+    }
+
+    /// Take the emulator back from the worker thread, if it is done
+    fn receive_finished_job(&mut self) {
+        let Ok(job) = self.finished_jobs.pop() else {
+            return;
+        };
+        match (job.task, job.result) {
+            (_, Err(err)) => {
+                self.playing = false;
+                let _ = self.events.push(PlayerEvent::Error(err));
+            }
+            (Task::StartTrack(index), Ok(())) => {
+                let _ = self.events.push(PlayerEvent::TrackStarted(index));
+            }
+            (Task::Seek(_), Ok(())) => {}
+        }
+        self.ended = job.emu.track_ended();
+        self.emu = Some(job.emu);
+    }
+}
+
+/// Runs the [Task]s the audio thread hands over, until the [Engine] is dropped
+fn run_worker(mut jobs: Consumer<Job>, mut finished_jobs: Producer<Job>) {
+    while !jobs.is_abandoned() {
+        while let Ok(mut job) = jobs.pop() {
+            job.result = match job.task {
+                Task::StartTrack(index) => job.emu.start_track(index),
+                Task::Seek(position) => job.emu.seek(position),
+            };
+            // There is room, since the engine only sends a job while it has the emulator
+            let _ = finished_jobs.push(job);
+        }
+        thread::park_timeout(WORKER_POLL_INTERVAL);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::playback_limit::PlaybackLimit;
+    use crate::test_utils::*;
+
+    fn engine() -> (Engine, Producer<Command>, Consumer<PlayerEvent>) {
+        let mut emu = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        emu.start_track(0).unwrap();
+        let (commands, command_receiver) = RingBuffer::new(QUEUE_LEN);
+        let (event_sender, events) = RingBuffer::new(QUEUE_LEN);
+        let shared = Arc::new(Shared {
+            volume: AtomicU32::new(1.0_f32.to_bits()),
+            position: AtomicU32::new(0),
+        });
+        let engine = Engine::new(emu, command_receiver, event_sender, shared);
+        (engine, commands, events)
+    }
+
+    /// Render until every command was handled and the worker thread returned the emulator
+    fn settle(engine: &mut Engine) {
+        for _ in 0..1000 {
+            engine.render(&mut [0.0_f32; 16]);
+            if engine.emu.is_some() && engine.commands.is_empty() {
+                return;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+        panic!("The worker thread didn't return the emulator");
+    }
+
+    #[test]
+    fn test_pause_and_play() {
+        let (mut engine, mut commands, _events) = engine();
+        let mut output = vec![0_i16; 4096];
+        engine.render(&mut output);
+        assert!(output.iter().all(|&sample| sample == 0));
+
+        commands.push(Command::Play).unwrap();
+        for _ in 0..20 {
+            engine.render(&mut output);
+        }
+        assert!(output.iter().any(|&sample| sample != 0));
+        assert!(engine.shared.position.load(Ordering::Relaxed) > 0);
+
+        let mut unsigned = vec![0_u16; 4096];
+        engine.render(&mut unsigned);
+        assert!(unsigned.iter().any(|&sample| sample != 0x8000));
+
+        commands.push(Command::Stop).unwrap();
+        settle(&mut engine);
+        engine.render(&mut output);
+        assert!(output.iter().all(|&sample| sample == 0));
+        assert_eq!(engine.emu.as_ref().unwrap().tell(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_volume_keeps_gain() {
+        let (mut engine, mut commands, _events) = engine();
+        engine.emu.as_mut().unwrap().set_gain(0.5);
+        commands.push(Command::Play).unwrap();
+        let mut output = vec![0.0_f32; 4096];
+        for _ in 0..20 {
+            engine.render(&mut output);
+        }
+        assert!(output.iter().any(|&sample| sample != 0.0));
+        assert_eq!(engine.emu.as_ref().unwrap().gain(), 0.5);
+
+        engine
+            .shared
+            .volume
+            .store(0.0_f32.to_bits(), Ordering::Relaxed);
+        engine.render(&mut output);
+        assert!(output.iter().all(|&sample| sample == 0.0));
+    }
+
+    #[test]
+    fn test_silent_while_seeking() {
+        let (mut engine, mut commands, _events) = engine();
+        commands.push(Command::Play).unwrap();
+        commands
+            .push(Command::Seek(Duration::from_secs(5)))
+            .unwrap();
+        // The emulator comes back from the worker thread at the start of a later callback
+        let mut output = vec![0.0_f32; 4096];
+        engine.render(&mut output);
+        assert!(output.iter().all(|&sample| sample == 0.0));
+        settle(&mut engine);
+        assert!(engine.emu.as_ref().unwrap().tell() >= Duration::from_secs(5));
+        assert!(engine.playing);
+    }
+
+    #[test]
+    fn test_track_events() {
+        let (mut engine, mut commands, mut events) = engine();
+        // The test file has a single track, so skipping does nothing
+        commands.push(Command::NextTrack).unwrap();
+        commands.push(Command::PreviousTrack).unwrap();
+        commands.push(Command::StartTrack(0)).unwrap();
+        commands.push(Command::StartTrack(99)).unwrap();
+        settle(&mut engine);
+        assert!(matches!(events.pop(), Ok(PlayerEvent::TrackStarted(0))));
+        assert!(matches!(events.pop(), Ok(PlayerEvent::Error(_))));
+        assert!(events.pop().is_err());
+
+        let emu = engine.emu.as_mut().unwrap();
+        emu.set_playback_fade_length(Duration::ZERO);
+        emu.set_playback_limit(PlaybackLimit::Fixed(Duration::from_millis(100)));
+        commands.push(Command::StartTrack(0)).unwrap();
+        commands.push(Command::Play).unwrap();
+        settle(&mut engine);
+        let mut output = vec![0.0_f32; 4096];
+        for _ in 0..100 {
+            engine.render(&mut output);
+        }
+        assert!(matches!(events.pop(), Ok(PlayerEvent::TrackStarted(0))));
+        assert!(matches!(events.pop(), Ok(PlayerEvent::TrackEnded(0))));
+        assert!(output.iter().all(|&sample| sample == 0.0));
+    }
+}
@@ -0,0 +1,276 @@
+use crate::error::GmeResult;
+use std::f64::consts::PI;
+
+/// Kernel taps on each side of an output frame when not downsampling. Downsampling widens the
+/// kernel so the lower cutoff keeps the same steepness.
+const HALF_TAPS: usize = 16;
+/// Kernel phases in the table. Phases in between are interpolated.
+const PHASES: usize = 256;
+/// Cutoff relative to the lower of the two Nyquist frequencies. Slightly below 1 so the
+/// transition band ends before frequencies that would alias.
+const CUTOFF: f64 = 0.95;
+/// Frames requested from the emulator at a time
+const FILL_FRAMES: usize = 512;
+/// Largest ratio between the input and output rates, either way. The kernel widens with the
+/// ratio when downsampling, so this bounds its size.
+pub(crate) const MAX_RATIO: u32 = 8;
+
+/// Converts stereo 16-bit audio from one sample rate to another with a windowed sinc filter. The
+/// output rate can change at any time without a gap in the audio. Only changing the rate
+/// allocates.
+#[derive(Clone, Debug)]
+pub(crate) struct Resampler {
+    input_rate: u32,
+    output_rate: u32,
+    /// Input frames per output frame
+    step: f64,
+    /// Taps on each side of an output frame
+    half_taps: usize,
+    /// `PHASES + 1` rows of `2 * half_taps` coefficients
+    table: Vec<f32>,
+    /// Input frames that are still needed, starting with `half_taps` frames of history. Has
+    /// room for the most frames [Self::process] keeps, so it never grows while processing.
+    input: Vec<[f32; 2]>,
+    /// Position of the next output frame, in frames from the start of `input`
+    position: f64,
+    scratch: Vec<i16>,
+}
+
+impl Resampler {
+    pub(crate) fn new(input_rate: u32, output_rate: u32) -> Self {
+        let mut resampler = Self {
+            input_rate,
+            output_rate,
+            step: 1.0,
+            half_taps: 0,
+            table: Vec::new(),
+            input: Vec::new(),
+            position: 0.0,
+            scratch: vec![0; FILL_FRAMES * 2],
+        };
+        resampler.set_output_rate(output_rate);
+        resampler.reset();
+        resampler
+    }
+
+    pub(crate) fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+
+    /// Change the output rate, keeping the audio that is buffered. The ratio to the input rate
+    /// must be at most [MAX_RATIO].
+    pub(crate) fn set_output_rate(&mut self, output_rate: u32) {
+        self.output_rate = output_rate;
+        self.step = self.input_rate as f64 / output_rate as f64;
+        let cutoff = CUTOFF * (1.0 / self.step).min(1.0);
+        let half_taps = (HALF_TAPS as f64 / cutoff).ceil() as usize;
+        if half_taps > self.half_taps && !self.input.is_empty() {
+            // The wider kernel reaches further back than the history that was kept
+            let extra = half_taps - self.half_taps;
+            self.input
+                .splice(0..0, std::iter::repeat_n([0.0; 2], extra));
+            self.position += extra as f64;
+        }
+        self.half_taps = half_taps;
+        self.table = kernel_table(half_taps, cutoff);
+        // Enough for the history on both sides of an output frame and one fill
+        let capacity = half_taps * 2 + FILL_FRAMES + 1;
+        self.input
+            .reserve(capacity.saturating_sub(self.input.len()));
+    }
+
+    /// Discard buffered audio, such as after seeking
+    pub(crate) fn reset(&mut self) {
+        self.input.clear();
+        self.input.resize(self.half_taps, [0.0; 2]);
+        self.position = self.half_taps as f64;
+    }
+
+    /// Input frames that were generated but not output yet
+    pub(crate) fn buffered_frames(&self) -> f64 {
+        (self.input.len() as f64 - self.position).max(0.0)
+    }
+
+    /// Fill `output` with stereo samples at the output rate, calling `fill` for more samples at
+    /// the input rate as needed
+    pub(crate) fn process(
+        &mut self,
+        output: &mut [i16],
+        mut fill: impl FnMut(&mut [i16]) -> GmeResult<()>,
+    ) -> GmeResult<()> {
+        let taps = self.half_taps * 2;
+        for frame in output.chunks_exact_mut(2) {
+            if self.input.len() < self.position as usize + self.half_taps + 1 {
+                self.drop_consumed();
+                while self.input.len() < self.position as usize + self.half_taps + 1 {
+                    fill(&mut self.scratch)?;
+                    self.input.extend(
+                        self.scratch
+                            .chunks_exact(2)
+                            .map(|pair| [pair[0] as f32, pair[1] as f32]),
+                    );
+                }
+            }
+
+            let base = self.position as usize;
+
+            let phase = self.position.fract() * PHASES as f64;
+            let row = phase as usize;
+            let t = (phase - row as f64) as f32;
+            let before = &self.table[row * taps..][..taps];
+            let after = &self.table[(row + 1) * taps..][..taps];
+            let first = base + 1 - self.half_taps;
+            let mut sum = [0.0_f32; 2];
+            for (j, input) in self.input[first..first + taps].iter().enumerate() {
+                let coefficient = before[j] + (after[j] - before[j]) * t;
+                sum[0] += input[0] * coefficient;
+                sum[1] += input[1] * coefficient;
+            }
+            frame[0] = sum[0].round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+            frame[1] = sum[1].round().clamp(i16::MIN as f32, i16::MAX as f32) as i16;
+            self.position += self.step;
+        }
+        Ok(())
+    }
+
+    /// Drop frames that are no longer in reach of the kernel
+    fn drop_consumed(&mut self) {
+        let consumed = (self.position as usize + 1)
+            .saturating_sub(self.half_taps)
+            .min(self.input.len());
+        if consumed > 0 {
+            self.input.drain(..consumed);
+            self.position -= consumed as f64;
+        }
+    }
+}
+
+/// Coefficients of a Blackman-windowed sinc low-pass filter. Row `i` is for an output frame
+/// `i / PHASES` of a frame after the input frame at tap `half_taps - 1`.
+fn kernel_table(half_taps: usize, cutoff: f64) -> Vec<f32> {
+    let taps = half_taps * 2;
+    let mut table = Vec::with_capacity((PHASES + 1) * taps);
+    for row in 0..=PHASES {
+        let fraction = row as f64 / PHASES as f64;
+        let coefficients: Vec<f64> = (0..taps)
+            .map(|j| {
+                let x = j as f64 - (half_taps - 1) as f64 - fraction;
+                sinc(cutoff * x) * blackman(x / half_taps as f64)
+            })
+            .collect();
+        // Normalize so a constant signal keeps its level at every phase
+        let sum: f64 = coefficients.iter().sum();
+        table.extend(coefficients.iter().map(|c| (c / sum) as f32));
+    }
+    table
+}
+
+fn sinc(x: f64) -> f64 {
+    if x == 0.0 {
+        1.0
+    } else {
+        (PI * x).sin() / (PI * x)
+    }
+}
+
+/// Blackman window over -1..=1
+fn blackman(x: f64) -> f64 {
+    if x.abs() >= 1.0 {
+        return 0.0;
+    }
+    let angle = PI * (x + 1.0);
+    0.42 - 0.5 * angle.cos() + 0.08 * (2.0 * angle).cos()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AMPLITUDE: f64 = 10000.0;
+
+    /// Resamples 0.2 seconds of a sine wave. Returns the left channel with the index of each
+    /// frame, skipping the start, where the history before the signal is silent.
+    fn resample_sine(input_rate: u32, output_rate: u32, frequency: f64) -> Vec<(usize, i16)> {
+        let mut input_frame = 0;
+        let fill = |buffer: &mut [i16]| {
+            for frame in buffer.chunks_exact_mut(2) {
+                let time = input_frame as f64 / input_rate as f64;
+                frame.fill((AMPLITUDE * (2.0 * PI * frequency * time).sin()) as i16);
+                input_frame += 1;
+            }
+            Ok(())
+        };
+        let mut resampler = Resampler::new(input_rate, output_rate);
+        let mut output = vec![0_i16; output_rate as usize / 5 * 2];
+        resampler.process(&mut output, fill).unwrap();
+        let skip = resampler.half_taps * 4;
+        output
+            .chunks_exact(2)
+            .map(|frame| frame[0])
+            .enumerate()
+            .skip(skip)
+            .collect()
+    }
+
+    /// Largest difference between a resampled sine wave and the ideal one
+    fn sine_error(input_rate: u32, output_rate: u32, frequency: f64) -> f64 {
+        resample_sine(input_rate, output_rate, frequency)
+            .into_iter()
+            .map(|(index, sample)| {
+                let time = index as f64 / output_rate as f64;
+                let expected = AMPLITUDE * (2.0 * PI * frequency * time).sin();
+                (sample as f64 - expected).abs()
+            })
+            .fold(0.0, f64::max)
+    }
+
+    #[test]
+    fn test_sine() {
+        // Within 0.2% of full scale
+        assert!(sine_error(22050, 44100, 1000.0) < 60.0);
+        assert!(sine_error(44100, 48000, 3000.0) < 60.0);
+        assert!(sine_error(48000, 32000, 2000.0) < 60.0);
+        assert!(sine_error(44100, 44100, 5000.0) < 2.0);
+    }
+
+    #[test]
+    fn test_removes_frequencies_above_nyquist() {
+        // 15 kHz can't be represented at 16 kHz, so it should be filtered out, not aliased
+        let output = resample_sine(44100, 16000, 15000.0);
+        let peak = output.iter().map(|(_, sample)| sample.unsigned_abs()).max();
+        assert!(peak.unwrap() < 100, "{peak:?}");
+    }
+
+    #[test]
+    fn test_input_never_grows() {
+        for output_rate in [44100 / MAX_RATIO, 32000, 48000, 44100 * MAX_RATIO] {
+            let mut resampler = Resampler::new(44100, output_rate);
+            let capacity = resampler.input.capacity();
+            let mut output = vec![0_i16; output_rate as usize * 2];
+            resampler.process(&mut output, |_| Ok(())).unwrap();
+            assert_eq!(resampler.input.capacity(), capacity, "{output_rate}");
+        }
+    }
+
+    #[test]
+    fn test_buffered_frames() {
+        let mut resampler = Resampler::new(44100, 48000);
+        let mut filled = 0;
+        let mut output = vec![0_i16; 9600];
+        resampler
+            .process(&mut output, |buffer| {
+                filled += buffer.len() / 2;
+                Ok(())
+            })
+            .unwrap();
+        let played = filled as f64 - resampler.buffered_frames();
+        assert!(
+            (played - 4800.0 * 44100.0 / 48000.0).abs() < 1.0,
+            "{played}"
+        );
+
+        resampler.set_output_rate(22050);
+        resampler.reset();
+        assert_eq!(resampler.buffered_frames(), 0.0);
+    }
+}
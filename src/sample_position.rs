@@ -0,0 +1,65 @@
+use std::time::Duration;
+
+/// A position in a track measured in frames, where a frame holds one sample for each output
+/// channel. Game Music Emu itself counts samples, so its positions depend on whether the
+/// emulator is in multi-channel mode. Counting frames keeps positions and durations the same in
+/// both modes, and keeping them in their own type stops them from being mixed up with
+/// milliseconds.
+#[derive(Copy, Clone, Default, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct SamplePosition(pub u32);
+
+impl SamplePosition {
+    /// The beginning of a track
+    pub const ZERO: Self = Self(0);
+
+    /// The position of the start of frame `frames`
+    pub fn from_frames(frames: u32) -> Self {
+        Self(frames)
+    }
+
+    /// Number of whole frames
+    pub fn frames(self) -> u32 {
+        self.0
+    }
+
+    /// Number of samples with `channels` output channels, counting each channel separately
+    pub fn samples(self, channels: u32) -> u32 {
+        self.0.saturating_mul(channels)
+    }
+
+    /// The position reached after playing `duration` of audio at `sample_rate`
+    pub fn from_duration(duration: Duration, sample_rate: u32) -> Self {
+        let frames = duration.as_nanos() * sample_rate as u128 / 1_000_000_000;
+        Self::from_frames(frames.min(u32::MAX as u128) as u32)
+    }
+
+    /// How long it takes to play up to this position at `sample_rate`. A sample rate of 0 gives
+    /// [Duration::ZERO].
+    pub fn to_duration(self, sample_rate: u32) -> Duration {
+        match sample_rate {
+            0 => Duration::ZERO,
+            rate => Duration::from_nanos(self.0 as u64 * 1_000_000_000 / rate as u64),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frames() {
+        let position = SamplePosition::from_frames(100);
+        assert_eq!(position.samples(2), 200);
+        assert_eq!(position.samples(16), 1600);
+        assert_eq!(position.frames(), 100);
+    }
+
+    #[test]
+    fn test_duration_round_trip() {
+        let position = SamplePosition::from_duration(Duration::from_secs(2), 44100);
+        assert_eq!(position.frames(), 88200);
+        assert_eq!(position.to_duration(44100), Duration::from_secs(2));
+        assert_eq!(position.to_duration(0), Duration::ZERO);
+    }
+}
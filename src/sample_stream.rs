@@ -0,0 +1,184 @@
+use crate::error::{GmeError, GmeErrorKind, GmeResult};
+use crate::wrapper::GameMusicEmu;
+use std::io;
+
+/// Frames generated at a time
+const BUFFER_FRAMES: usize = 4096;
+
+/// Pulls audio from a started track, either as stereo frames with [Iterator] or as little-endian
+/// 16-bit PCM bytes with [io::Read]. Audio is generated in blocks of a few thousand frames.
+///
+/// The stream ends when [GameMusicEmu::track_ended] returns true, unless it is
+/// [endless](Self::set_endless). Reading frames after reading bytes skips the rest of a frame
+/// that was partly read.
+///
+/// [Iterator] and [io::Read] both have `take` and `by_ref` methods, so calling them needs the
+/// trait name, as in `Iterator::take(stream, 1000)`, when both traits are in scope.
+pub struct SampleStream {
+    emu: GameMusicEmu,
+    buffer: Box<[i16]>,
+    /// Number of samples in `buffer` that were generated
+    len: usize,
+    /// Byte offset of the next unread byte in `buffer`
+    position: usize,
+    endless: bool,
+    error: Option<GmeError>,
+}
+
+impl SampleStream {
+    /// Stream the current track of `emu`. Fails with [GmeErrorKind::Unsupported] if `emu` was
+    /// created with [GameMusicEmu::new_multi_channel].
+    pub fn new(emu: GameMusicEmu) -> GmeResult<Self> {
+        if emu.is_multi_channel() {
+            return Err(GmeError::with_kind(
+                GmeErrorKind::Unsupported,
+                "Multi-channel emulators can't be streamed as stereo",
+            ));
+        }
+        Ok(Self {
+            emu,
+            buffer: vec![0; BUFFER_FRAMES * 2].into_boxed_slice(),
+            len: 0,
+            position: 0,
+            endless: false,
+            error: None,
+        })
+    }
+
+    /// Keep streaming after the track ends. The emulator produces silence once the track has
+    /// faded out, so this is usually combined with [crate::PlaybackLimit::Infinite].
+    pub fn set_endless(&mut self, endless: bool) {
+        self.endless = endless;
+    }
+
+    pub fn is_endless(&self) -> bool {
+        self.endless
+    }
+
+    /// The error that ended the stream, if the emulator failed while iterating. [io::Read]
+    /// returns these errors directly instead.
+    pub fn take_error(&mut self) -> Option<GmeError> {
+        self.error.take()
+    }
+
+    pub fn emu(&self) -> &GameMusicEmu {
+        &self.emu
+    }
+
+    /// Audio that was already generated is still returned after changing the emulator
+    pub fn emu_mut(&mut self) -> &mut GameMusicEmu {
+        &mut self.emu
+    }
+
+    pub fn into_inner(self) -> GameMusicEmu {
+        self.emu
+    }
+
+    /// Generate more audio if everything generated was read. Returns `false` at the end of the
+    /// stream.
+    fn fill(&mut self) -> GmeResult<bool> {
+        if self.position < self.len * 2 {
+            return Ok(true);
+        }
+        if !self.endless && self.emu.track_ended() {
+            return Ok(false);
+        }
+        self.emu.play(&mut self.buffer)?;
+        self.len = self.buffer.len();
+        self.position = 0;
+        Ok(true)
+    }
+}
+
+impl Iterator for SampleStream {
+    type Item = [i16; 2];
+
+    fn next(&mut self) -> Option<[i16; 2]> {
+        // Skip the rest of a frame that was partly read as bytes
+        self.position = self.position.next_multiple_of(4);
+        match self.fill() {
+            Ok(true) => {}
+            Ok(false) => return None,
+            Err(err) => {
+                self.error = Some(err);
+                return None;
+            }
+        }
+        let index = self.position / 2;
+        self.position += 4;
+        Some([self.buffer[index], self.buffer[index + 1]])
+    }
+}
+
+impl io::Read for SampleStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || !self.fill().map_err(io::Error::other)? {
+            return Ok(0);
+        }
+        let start = self.position;
+        let end = (start + buf.len()).min(self.len * 2);
+        for (byte, position) in buf.iter_mut().zip(start..end) {
+            *byte = self.buffer[position / 2].to_le_bytes()[position % 2];
+        }
+        self.position = end;
+        Ok(end - start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::playback_limit::PlaybackLimit;
+    use crate::test_utils::*;
+    use std::io::Read;
+    use std::time::Duration;
+
+    fn started_emu() -> GameMusicEmu {
+        let mut emu = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        emu.start_track(0).unwrap();
+        emu
+    }
+
+    #[test]
+    fn test_frames_and_bytes_match_play() {
+        let mut expected = vec![0_i16; 20000];
+        started_emu().play(&mut expected).unwrap();
+
+        let stream = SampleStream::new(started_emu()).unwrap();
+        let frames: Vec<[i16; 2]> = Iterator::take(stream, 10000).collect();
+        assert_eq!(frames.as_flattened(), expected);
+
+        let mut stream = SampleStream::new(started_emu()).unwrap();
+        let mut bytes = vec![0_u8; 40000];
+        // Odd lengths split samples between reads
+        let mut read = 0;
+        while read < bytes.len() {
+            let end = (read + 333).min(bytes.len());
+            read += stream.read(&mut bytes[read..end]).unwrap();
+        }
+        let samples: Vec<i16> = bytes
+            .chunks_exact(2)
+            .map(|pair| i16::from_le_bytes([pair[0], pair[1]]))
+            .collect();
+        assert_eq!(samples, expected);
+    }
+
+    #[test]
+    fn test_ends_with_track() {
+        let mut emu = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        emu.set_playback_fade_length(Duration::ZERO);
+        emu.set_playback_limit(PlaybackLimit::Fixed(Duration::from_millis(200)));
+        emu.start_track(0).unwrap();
+        let mut stream = SampleStream::new(emu).unwrap();
+        let frames = Iterator::by_ref(&mut stream).count();
+        assert!(
+            frames >= 8820 && frames.is_multiple_of(BUFFER_FRAMES),
+            "{frames}"
+        );
+        assert_eq!(stream.read(&mut [0; 16]).unwrap(), 0);
+        assert!(stream.take_error().is_none());
+
+        stream.set_endless(true);
+        assert_eq!(Iterator::take(&mut stream, 50000).count(), 50000);
+    }
+}
@@ -0,0 +1,160 @@
+//! Collecting the metadata of every track in a directory of music files
+
+use crate::emu_track_info::EmuTrackInfo;
+use crate::emu_type::EmuType;
+use crate::error::GmeOrIoError;
+use crate::native::{identify_extension, identify_header};
+use crate::wrapper::GameMusicEmu;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// Sample rate files are loaded at. It doesn't affect the metadata.
+const SCAN_SAMPLE_RATE: u32 = 44100;
+
+/// Metadata of one track of a file
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TrackRecord {
+    pub path: PathBuf,
+    pub emu_type: EmuType,
+    /// Index of the track in the file, where 0 is the first track
+    pub track: usize,
+    pub track_count: usize,
+    pub info: EmuTrackInfo,
+    pub voice_names: Vec<String>,
+}
+
+/// A file or directory that couldn't be read
+#[derive(Debug)]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub error: GmeOrIoError,
+}
+
+/// The result of [scan_directory]
+#[derive(Debug, Default)]
+pub struct ScanReport {
+    pub tracks: Vec<TrackRecord>,
+    /// Files that look like music files but failed to load, and directories that couldn't be
+    /// listed. They don't stop the scan.
+    pub errors: Vec<ScanError>,
+}
+
+/// Find every music file under `directory`, including subdirectories, and collect the metadata
+/// of their tracks. Formats are detected from the file contents, or the extension for formats
+/// without a recognizable header. Other files are skipped. Files and directories are visited in
+/// order of their names, and symbolic links to directories are not followed.
+pub fn scan_directory(directory: impl AsRef<Path>) -> ScanReport {
+    let mut report = ScanReport::default();
+    scan_into(directory.as_ref(), &mut report);
+    report
+}
+
+fn scan_into(directory: &Path, report: &mut ScanReport) {
+    let entries = match list_directory(directory) {
+        Ok(entries) => entries,
+        Err(err) => {
+            report.errors.push(ScanError {
+                path: directory.to_path_buf(),
+                error: err.into(),
+            });
+            return;
+        }
+    };
+    for (path, is_dir) in entries {
+        if is_dir {
+            scan_into(&path, report);
+            continue;
+        }
+        match scan_file(&path) {
+            Ok(tracks) => report.tracks.extend(tracks),
+            Err(error) => report.errors.push(ScanError { path, error }),
+        }
+    }
+}
+
+/// Paths in `directory` sorted by name, with whether each one is a directory
+fn list_directory(directory: &Path) -> std::io::Result<Vec<(PathBuf, bool)>> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(directory)? {
+        let entry = entry?;
+        entries.push((entry.path(), entry.file_type()?.is_dir()));
+    }
+    entries.sort();
+    Ok(entries)
+}
+
+/// Collect the metadata of every track in the file at `path`. Returns no tracks if the file
+/// isn't a recognized music file, after reading only its first few bytes.
+pub fn scan_file(path: impl AsRef<Path>) -> Result<Vec<TrackRecord>, GmeOrIoError> {
+    let path = path.as_ref();
+    let mut file = File::open(path)?;
+    let mut data = Vec::new();
+    (&mut file).take(4).read_to_end(&mut data)?;
+    let emu_type = identify_header(&data).or_else(|| identify_extension(path.to_str()?));
+    let Some(emu_type) = emu_type else {
+        return Ok(Vec::new());
+    };
+    file.read_to_end(&mut data)?;
+    let mut emu = GameMusicEmu::new(emu_type, SCAN_SAMPLE_RATE)?;
+    emu.load_data(&data)?;
+
+    let track_count = emu.track_count();
+    let voice_names = emu.voice_names();
+    (0..track_count)
+        .map(|track| {
+            Ok(TrackRecord {
+                path: path.to_path_buf(),
+                emu_type,
+                track,
+                track_count,
+                info: emu.track_info(track as u32)?,
+                voice_names: voice_names.clone(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+
+    #[test]
+    fn test_scan_directory() {
+        let directory = temp_path("scan_directory");
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("nested")).unwrap();
+        fs::copy(TEST_NSF_PATH, directory.join("a.nsf")).unwrap();
+        // Found by its header, despite the extension
+        fs::copy(TEST_NSF_PATH, directory.join("nested/b.bin")).unwrap();
+        fs::write(directory.join("broken.nsf"), b"not music").unwrap();
+        fs::write(directory.join("notes.txt"), b"not music").unwrap();
+        fs::write(directory.join("short"), b"ab").unwrap();
+
+        let report = scan_directory(&directory);
+        fs::remove_dir_all(&directory).unwrap();
+
+        let paths: Vec<_> = report.tracks.iter().map(|track| &track.path).collect();
+        assert_eq!(
+            paths,
+            [&directory.join("a.nsf"), &directory.join("nested/b.bin")]
+        );
+        let track = &report.tracks[0];
+        assert_eq!(track.emu_type, EmuType::Nsf);
+        assert_eq!(track.track_count, 1);
+        assert_eq!(track.info.game.as_deref(), Some("Tetris (GB)"));
+        assert_eq!(track.voice_names.len(), 5);
+
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].path, directory.join("broken.nsf"));
+    }
+
+    #[test]
+    fn test_missing_directory() {
+        let report = scan_directory("/nonexistent/gme");
+        assert!(report.tracks.is_empty());
+        assert!(matches!(report.errors[0].error, GmeOrIoError::IoError(_)));
+    }
+}
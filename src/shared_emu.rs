@@ -0,0 +1,80 @@
+use crate::wrapper::GameMusicEmu;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+
+/// A [GameMusicEmu] that can be cloned and shared between threads. Access is guarded by a
+/// mutex, so only one thread uses the emulator at a time.
+#[derive(Clone)]
+pub struct SharedGameMusicEmu {
+    emu: Arc<Mutex<GameMusicEmu>>,
+}
+
+impl SharedGameMusicEmu {
+    pub fn new(emu: GameMusicEmu) -> Self {
+        Self {
+            emu: Arc::new(Mutex::new(emu)),
+        }
+    }
+
+    /// Lock the emulator, blocking until no other thread is using it
+    pub fn lock(&self) -> MutexGuard<'_, GameMusicEmu> {
+        // A panic while the lock was held can't leave the emulator in a state that is unsafe to
+        // use, so poisoning is ignored.
+        self.emu.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Lock the emulator if no other thread is using it. Useful in audio callbacks, which should
+    /// not block.
+    pub fn try_lock(&self) -> Option<MutexGuard<'_, GameMusicEmu>> {
+        match self.emu.try_lock() {
+            Ok(guard) => Some(guard),
+            Err(TryLockError::Poisoned(err)) => Some(err.into_inner()),
+            Err(TryLockError::WouldBlock) => None,
+        }
+    }
+
+    /// Get the emulator back if this is the last clone
+    pub fn into_inner(self) -> Result<GameMusicEmu, Self> {
+        Arc::try_unwrap(self.emu)
+            .map(|emu| emu.into_inner().unwrap_or_else(PoisonError::into_inner))
+            .map_err(|emu| Self { emu })
+    }
+}
+
+impl From<GameMusicEmu> for SharedGameMusicEmu {
+    fn from(emu: GameMusicEmu) -> Self {
+        Self::new(emu)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_shared_between_threads() {
+        let shared =
+            SharedGameMusicEmu::new(GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap());
+        shared.lock().start_track(0).unwrap();
+        let clone = shared.clone();
+        std::thread::spawn(move || {
+            let mut buffer = [0_i16; 512];
+            clone.lock().play(&mut buffer).unwrap();
+        })
+        .join()
+        .unwrap();
+        assert!(shared.lock().tell() > Duration::ZERO);
+        assert!(shared.into_inner().is_ok());
+    }
+
+    #[test]
+    fn test_try_lock() {
+        let shared =
+            SharedGameMusicEmu::new(GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap());
+        let guard = shared.lock();
+        assert!(shared.try_lock().is_none());
+        drop(guard);
+        assert!(shared.try_lock().is_some());
+    }
+}
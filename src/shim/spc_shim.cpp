@@ -0,0 +1,15 @@
+// C entry points for Spc_Emu features that gme.h doesn't expose
+
+#include "gme.h"
+#include "Spc_Emu.h"
+
+extern "C" {
+
+// Prevents channels and global volumes from being phase-negated. Does nothing if emu isn't SPC.
+BLARGG_EXPORT void gme_spc_disable_surround( Music_Emu* emu, int disable )
+{
+	if ( emu->type() == gme_spc_type )
+		static_cast<Spc_Emu*>( emu )->disable_surround( disable != 0 );
+}
+
+}
@@ -0,0 +1,36 @@
+use crate::native;
+use crate::native::EmuHandle;
+
+/// Settings that only apply to the Super Nintendo SPC emulator. Get one from
+/// [crate::GameMusicEmu::spc_options].
+///
+/// Settings stay in effect across tracks and files until they are changed again.
+pub struct SpcOptions<'a> {
+    handle: &'a mut EmuHandle,
+}
+
+impl<'a> SpcOptions<'a> {
+    pub(crate) fn new(handle: &'a mut EmuHandle) -> Self {
+        Self { handle }
+    }
+
+    /// Prevent channels and global volumes from being phase-negated. Many SNES tracks use
+    /// negated volumes for a surround effect, which can sound wrong on headphones.
+    pub fn disable_surround(&mut self, disable: bool) -> &mut Self {
+        native::spc_disable_surround(self.handle, disable);
+        self
+    }
+
+    /// Turn the DSP's echo effect off, leaving the dry signal
+    pub fn disable_echo(&mut self, disable: bool) -> &mut Self {
+        native::disable_echo(self.handle, disable);
+        self
+    }
+
+    /// Run the output through a low-pass and high-pass filter that better matches the sound
+    /// of a real SNES. This is what accuracy mode does for SPC, and it is off by default.
+    pub fn enable_filter(&mut self, enable: bool) -> &mut Self {
+        native::enable_accuracy(self.handle, enable);
+        self
+    }
+}
@@ -0,0 +1,31 @@
+/// Number of stereo pairs a multi-channel emulator renders
+pub const STEM_COUNT: usize = 8;
+
+/// One frame of multi-channel output: a `[left, right]` pair for each stem. Voice `i` is rendered
+/// into stem `i % STEM_COUNT`.
+pub type StemFrame = [[i16; 2]; STEM_COUNT];
+
+/// Groups voice names by the stem they are rendered into
+pub(crate) fn stem_labels(
+    voice_names: impl IntoIterator<Item = String>,
+) -> [Vec<String>; STEM_COUNT] {
+    let mut labels: [Vec<String>; STEM_COUNT] = Default::default();
+    for (index, name) in voice_names.into_iter().enumerate() {
+        labels[index % STEM_COUNT].push(name);
+    }
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stem_labels() {
+        let names = (0..10).map(|i| format!("Voice {i}"));
+        let labels = stem_labels(names);
+        assert_eq!(labels[0], ["Voice 0", "Voice 8"]);
+        assert_eq!(labels[1], ["Voice 1", "Voice 9"]);
+        assert_eq!(labels[7], ["Voice 7"]);
+    }
+}
@@ -1,5 +1,7 @@
 #![cfg(test)]
 
+use std::path::PathBuf;
+
 // The location of the test nsf file
 pub const TEST_NSF_PATH: &str = "assets/test.nsf";
 
@@ -15,3 +17,9 @@ pub fn get_test_nsf_data() -> Vec<u8> {
 pub fn get_test_m3u_data() -> Vec<u8> {
     std::fs::read(TEST_M3U_PATH).unwrap()
 }
+
+/// A path named `name` in the temporary directory, which differs for every test process so
+/// test runs can't collide
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("gme_test_{}_{}", std::process::id(), name))
+}
@@ -0,0 +1,156 @@
+use std::ops::{BitAnd, BitOr, Not, Sub};
+
+/// A set of voices, where bit `i` stands for voice `i`. Used for muting voices with
+/// [crate::GameMusicEmu::mute_voices].
+///
+/// Game Music Emu stores masks in an `int`, so only voices below [VoiceMask::MAX_VOICES] can be
+/// in a mask. Others are ignored.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(transparent)
+)]
+pub struct VoiceMask(u32);
+
+impl VoiceMask {
+    pub const MAX_VOICES: u32 = 32;
+
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    /// Every voice of an emulator with `voice_count` voices
+    pub fn all(voice_count: u32) -> Self {
+        Self(bit(voice_count).wrapping_sub(1))
+    }
+
+    pub fn single(voice: u32) -> Self {
+        Self(bit(voice))
+    }
+
+    pub const fn from_bits(bits: u32) -> Self {
+        Self(bits)
+    }
+
+    pub const fn bits(&self) -> u32 {
+        self.0
+    }
+
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(&self, voice: u32) -> bool {
+        self.0 & bit(voice) != 0
+    }
+
+    pub fn insert(&mut self, voice: u32) {
+        self.0 |= bit(voice);
+    }
+
+    pub fn remove(&mut self, voice: u32) {
+        self.0 &= !bit(voice);
+    }
+
+    /// Inserts `voice` if `value` is true, otherwise removes it
+    pub fn set(&mut self, voice: u32, value: bool) {
+        if value {
+            self.insert(voice);
+        } else {
+            self.remove(voice);
+        }
+    }
+
+    /// The voices in the mask, from lowest to highest
+    pub fn iter(&self) -> impl Iterator<Item = u32> + use<> {
+        let bits = self.0;
+        (0..Self::MAX_VOICES).filter(move |&voice| bits & bit(voice) != 0)
+    }
+}
+
+/// Bit for `voice`, or 0 if it can't be in a mask. `bit(MAX_VOICES)` wraps to 0, which lets
+/// [VoiceMask::all] include all 32 voices.
+fn bit(voice: u32) -> u32 {
+    1_u32.checked_shl(voice).unwrap_or(0)
+}
+
+impl BitOr for VoiceMask {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+impl BitAnd for VoiceMask {
+    type Output = Self;
+
+    fn bitand(self, rhs: Self) -> Self {
+        Self(self.0 & rhs.0)
+    }
+}
+
+impl Sub for VoiceMask {
+    type Output = Self;
+
+    fn sub(self, rhs: Self) -> Self {
+        Self(self.0 & !rhs.0)
+    }
+}
+
+/// Every voice that isn't in the mask, up to [VoiceMask::MAX_VOICES]. Combine with
+/// [VoiceMask::all] to limit it to an emulator's voices.
+impl Not for VoiceMask {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Self(!self.0)
+    }
+}
+
+impl FromIterator<u32> for VoiceMask {
+    fn from_iter<T: IntoIterator<Item = u32>>(iter: T) -> Self {
+        let mut mask = Self::empty();
+        for voice in iter {
+            mask.insert(voice);
+        }
+        mask
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_all() {
+        assert_eq!(VoiceMask::all(0), VoiceMask::empty());
+        assert_eq!(VoiceMask::all(5).bits(), 0b11111);
+        assert_eq!(VoiceMask::all(32).bits(), u32::MAX);
+        assert_eq!(VoiceMask::all(40).bits(), u32::MAX);
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut mask = VoiceMask::empty();
+        mask.insert(1);
+        mask.set(3, true);
+        assert!(mask.contains(1) && mask.contains(3) && !mask.contains(2));
+        assert_eq!(mask.iter().collect::<Vec<_>>(), [1, 3]);
+        mask.remove(1);
+        assert_eq!(mask, VoiceMask::single(3));
+        mask.insert(40);
+        assert!(!mask.contains(40));
+    }
+
+    #[test]
+    fn test_operators() {
+        let all = VoiceMask::all(4);
+        let solo = all - VoiceMask::single(2);
+        assert_eq!(solo.bits(), 0b1011);
+        assert_eq!(!VoiceMask::single(2) & all, solo);
+        assert_eq!(solo | VoiceMask::single(2), all);
+        assert_eq!([0, 1, 3].into_iter().collect::<VoiceMask>(), solo);
+    }
+}
@@ -1,64 +1,407 @@
 use crate::emu_equalizer::EmuEqualizer;
 use crate::emu_track_info::EmuTrackInfo;
 use crate::emu_type::EmuType;
+use crate::fade_in::FadeIn;
+use crate::float_output::FloatOutput;
 use crate::native::EmuHandle;
-use crate::{GmeOrIoError, GmeResult, native};
+use crate::playback_limit::PlaybackLimit;
+use crate::resampler::{MAX_RATIO, Resampler};
+use crate::sample_position::SamplePosition;
+#[cfg(feature = "spc")]
+use crate::spc_options::SpcOptions;
+use crate::stems::{STEM_COUNT, StemFrame, stem_labels};
+use crate::voice_mask::VoiceMask;
+use crate::{GmeError, GmeErrorKind, GmeOrIoError, GmeResult, native};
+use std::io::{Read, Seek, SeekFrom};
+use std::ops::RangeInclusive;
 use std::path::Path;
+use std::time::Duration;
 
 /// Provides a wrapper around native functions that take an `EmuHandle`
-#[derive(Clone)]
+///
+/// A `GameMusicEmu` uniquely owns its emulator, so anything that changes its state takes
+/// `&mut self`. It can be moved to another thread, such as an audio thread. Use
+/// [crate::SharedGameMusicEmu] when more than one thread needs access to the same instance.
 pub struct GameMusicEmu {
     handle: EmuHandle,
+    /// Rate the emulator runs at, which can't change after it is created
+    sample_rate: u32,
+    /// Converts to the rate set with [Self::set_sample_rate] when it differs from `sample_rate`
+    resampler: Option<Resampler>,
+    /// Game Music Emu's tempo, which [Self::tell_scaled] needs when resampling
+    tempo: f64,
+    fade_in: FadeIn,
+    float_output: FloatOutput,
+    playback_limit: PlaybackLimit,
+    playback_fade_length: Duration,
+    ignore_silence: bool,
+    /// Game Music Emu has no getter for its mute mask, so it is tracked here too
+    muted_voices: VoiceMask,
+    current_track: Option<usize>,
+    /// Warnings recorded since they were last taken. `None` unless collecting is enabled.
+    warnings: Option<Vec<String>>,
 }
 
 impl GameMusicEmu {
-    /// Create an instance for the specified [crate::EmuType]
-    pub fn new(emu_type: EmuType, sample_rate: u32) -> Self {
+    fn from_handle(handle: EmuHandle, sample_rate: u32) -> Self {
         Self {
-            handle: native::new_emu(emu_type, sample_rate),
+            handle,
+            sample_rate,
+            resampler: None,
+            tempo: 1.0,
+            fade_in: FadeIn::default(),
+            float_output: FloatOutput::default(),
+            playback_limit: PlaybackLimit::default(),
+            playback_fade_length: DEFAULT_FADE_LENGTH,
+            ignore_silence: false,
+            muted_voices: VoiceMask::empty(),
+            current_track: None,
+            warnings: None,
         }
     }
 
+    /// Create an instance for the specified [crate::EmuType]. Fails with
+    /// [GmeErrorKind::InvalidArgument] if `sample_rate` is outside [SAMPLE_RATES], which is true
+    /// of every constructor.
+    pub fn new(emu_type: EmuType, sample_rate: u32) -> GmeResult<Self> {
+        check_sample_rate(sample_rate)?;
+        Ok(Self::from_handle(
+            native::new_emu(emu_type, sample_rate)?,
+            sample_rate,
+        ))
+    }
+
+    /// Create an instance that renders each voice into its own stereo pair, for use with
+    /// [Self::play_stems]. Fails with [crate::GmeErrorKind::Unsupported] if `emu_type` can't
+    /// render multi-channel, which currently includes SPC, GYM and VGM.
+    pub fn new_multi_channel(emu_type: EmuType, sample_rate: u32) -> GmeResult<Self> {
+        check_sample_rate(sample_rate)?;
+        Ok(Self::from_handle(
+            native::new_emu_multi_channel(emu_type, sample_rate)?,
+            sample_rate,
+        ))
+    }
+
     /// Creates a new instance by loading a file at the specified path
     pub fn from_file(
         path: impl AsRef<Path>,
         sample_rate: u32,
     ) -> Result<GameMusicEmu, GmeOrIoError> {
-        Ok(Self {
-            handle: native::open_file(path, sample_rate)?,
-        })
+        check_sample_rate(sample_rate)?;
+        Ok(Self::from_handle(
+            native::open_file(path, sample_rate)?,
+            sample_rate,
+        ))
     }
 
     /// Creates a new instance by loading data at the specified path
     pub fn from_data(data: impl AsRef<[u8]>, sample_rate: u32) -> GmeResult<GameMusicEmu> {
-        Ok(Self {
-            handle: native::open_data(data.as_ref(), sample_rate)?,
+        check_sample_rate(sample_rate)?;
+        Ok(Self::from_handle(
+            native::open_data(data.as_ref(), sample_rate)?,
+            sample_rate,
+        ))
+    }
+
+    /// Sample rate of the audio [Self::play] generates. This is the rate the instance was created
+    /// with, unless it was changed with [Self::set_sample_rate].
+    pub fn sample_rate(&self) -> u32 {
+        self.resampler
+            .as_ref()
+            .map_or(self.sample_rate, Resampler::output_rate)
+    }
+
+    /// Sample rate the emulator itself runs at, which is always the rate the instance was
+    /// created with
+    pub fn emulator_sample_rate(&self) -> u32 {
+        self.sample_rate
+    }
+
+    /// Change the sample rate of the audio [Self::play] generates. Game Music Emu can't change
+    /// its rate after it is created, so the emulator keeps running at
+    /// [Self::emulator_sample_rate] and its output is resampled. This can be done in the middle
+    /// of a track without a gap, and doesn't affect [Self::tell] or any other positions, which
+    /// stay in the emulator's time.
+    ///
+    /// Fails with [GmeErrorKind::InvalidArgument] if `rate` is more than 8 times higher or lower
+    /// than [Self::emulator_sample_rate], and with [GmeErrorKind::Unsupported] in multi-channel
+    /// mode.
+    pub fn set_sample_rate(&mut self, rate: u32) -> GmeResult<()> {
+        let native_rate = self.sample_rate as u64;
+        if (rate as u64) * (MAX_RATIO as u64) < native_rate
+            || rate as u64 > native_rate * MAX_RATIO as u64
+        {
+            return Err(GmeError::with_kind(
+                GmeErrorKind::InvalidArgument,
+                format!(
+                    "Sample rate {} is more than {} times higher or lower than {}",
+                    rate, MAX_RATIO, self.sample_rate
+                ),
+            ));
+        }
+        let multi_channel = self.is_multi_channel();
+        match &mut self.resampler {
+            Some(resampler) => resampler.set_output_rate(rate),
+            None if rate == self.sample_rate => {}
+            None if multi_channel => {
+                return Err(GmeError::with_kind(
+                    GmeErrorKind::Unsupported,
+                    "Multi-channel output can't be resampled",
+                ));
+            }
+            None => self.resampler = Some(Resampler::new(self.sample_rate, rate)),
+        }
+        Ok(())
+    }
+
+    /// Discards audio buffered for resampling, after the position jumps. Resampling stops if the
+    /// rates are the same again.
+    fn reset_resampler(&mut self) {
+        if let Some(resampler) = &mut self.resampler {
+            if resampler.output_rate() == self.sample_rate {
+                self.resampler = None;
+            } else {
+                resampler.reset();
+            }
+        }
+    }
+
+    /// How far the emulator has played ahead of [Self::play], because of the audio buffered
+    /// for resampling
+    fn resampler_delay(&self) -> Duration {
+        self.resampler.as_ref().map_or(Duration::ZERO, |resampler| {
+            Duration::from_secs_f64(resampler.buffered_frames() / self.sample_rate as f64)
         })
     }
 
     /// Load music file from memory into emulator. Makes a copy of data passed.
-    pub fn load_data(&self, data: impl AsRef<[u8]>) -> GmeResult<()> {
-        native::load_data(&self.handle, data.as_ref())
+    pub fn load_data(&mut self, data: impl AsRef<[u8]>) -> GmeResult<()> {
+        let result = native::load_data(&mut self.handle, data.as_ref());
+        self.record_warning();
+        result
     }
 
     /// Load music file into emulator
-    pub fn load_file(&self, path: impl AsRef<Path>) -> Result<(), GmeOrIoError> {
-        native::load_file(&self.handle, path)
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<(), GmeOrIoError> {
+        let result = native::load_file(&mut self.handle, path);
+        self.record_warning();
+        result
     }
 
-    /// Generate `count` 16-bit signed samples into `buffer`. Output is in stereo.
-    pub fn play(&self, count: usize, buffer: &mut [i16]) -> GmeResult<()> {
-        native::play(&self.handle, count, buffer)
+    /// Load several single-track files, such as a folder of SPC files, as the tracks of this
+    /// emulator. Only works for types with a fixed track count of 1 (SPC, GYM, VGM and VGZ);
+    /// other types fail with [GmeErrorKind::Unsupported]. Fails if any of the files can't be
+    /// loaded. Makes a copy of the data passed.
+    pub fn load_tracks(&mut self, tracks: &[impl AsRef<[u8]>]) -> GmeResult<()> {
+        let tracks = tracks.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+        let result = native::load_tracks(&mut self.handle, &tracks);
+        self.record_warning();
+        result
+    }
+
+    /// Load a music file by streaming it from `reader`, starting at its current position and
+    /// continuing to the end. Nothing is copied on the Rust side, though most emulators keep
+    /// their own copy of the data.
+    ///
+    /// Game Music Emu needs the size of the file before it reads any of it, so `reader` is
+    /// seeked to the end and back to find it. Use [Self::load_reader_with_size] for readers
+    /// that can't seek but know their size, such as archive entries.
+    pub fn load_reader(&mut self, mut reader: impl Read + Seek) -> Result<(), GmeOrIoError> {
+        let start = reader.stream_position()?;
+        let end = reader.seek(SeekFrom::End(0))?;
+        reader.seek(SeekFrom::Start(start))?;
+        self.load_reader_with_size(reader, end.saturating_sub(start))
+    }
+
+    /// Load a music file of `size` bytes by streaming it from `reader`, like
+    /// [Self::load_reader]. Fails with an [std::io::ErrorKind::UnexpectedEof] error if `reader`
+    /// ends before `size` bytes.
+    pub fn load_reader_with_size(
+        &mut self,
+        mut reader: impl Read,
+        size: u64,
+    ) -> Result<(), GmeOrIoError> {
+        let result = native::load_reader(&mut self.handle, &mut reader, size);
+        self.record_warning();
+        result
+    }
+
+    /// Fill `buffer` with 16-bit signed samples. Output is in stereo, so the length of `buffer`
+    /// must be a multiple of 2. In multi-channel mode it must be a multiple of 16 instead; see
+    /// [Self::play_stems].
+    pub fn play(&mut self, buffer: &mut [i16]) -> GmeResult<()> {
+        let Some(mut resampler) = self.resampler.take() else {
+            return self.play_native(buffer);
+        };
+        let result = native::check_buffer_len(&self.handle, buffer.len())
+            .and_then(|()| resampler.process(buffer, |input| self.play_native(input)));
+        self.resampler = Some(resampler);
+        result
+    }
+
+    /// Play at the emulator's own sample rate
+    fn play_native(&mut self, buffer: &mut [i16]) -> GmeResult<()> {
+        let result = native::play(&mut self.handle, buffer);
+        self.record_warning();
+        result?;
+        self.fade_in
+            .apply(buffer, native::out_channels(&self.handle));
+        Ok(())
+    }
+
+    /// Fill `buffer` with samples from -1.0 to 1.0, after applying [Self::set_gain] and
+    /// [Self::set_soft_clip]. The length rules are the same as for [Self::play]. Doesn't
+    /// allocate, so it is safe to call from an audio callback.
+    pub fn play_f32(&mut self, buffer: &mut [f32]) -> GmeResult<()> {
+        native::check_buffer_len(&self.handle, buffer.len())?;
+        let mut samples = [0_i16; F32_CHUNK_LEN];
+        for chunk in buffer.chunks_mut(F32_CHUNK_LEN) {
+            let samples = &mut samples[..chunk.len()];
+            self.play(samples)?;
+            self.float_output.convert(samples, chunk);
+        }
+        Ok(())
+    }
+
+    /// Set the gain [Self::play_f32] applies, where 1.0 leaves the level unchanged. Samples
+    /// that end up louder than full scale are clipped. [Self::play] is not affected.
+    pub fn set_gain(&mut self, gain: f32) {
+        self.float_output.gain = gain;
+    }
+
+    pub fn gain(&self) -> f32 {
+        self.float_output.gain
+    }
+
+    /// Make [Self::play_f32] compress loud peaks smoothly instead of clipping them hard at full
+    /// scale. Off by default. Samples below half of full scale are not changed.
+    pub fn set_soft_clip(&mut self, soft_clip: bool) {
+        self.float_output.soft_clip = soft_clip;
+    }
+
+    pub fn soft_clip(&self) -> bool {
+        self.float_output.soft_clip
+    }
+
+    /// True if this instance was created with [Self::new_multi_channel]
+    pub fn is_multi_channel(&self) -> bool {
+        native::multi_channel(&self.handle)
+    }
+
+    /// Fill `frames` with each stem rendered separately. Only works in multi-channel mode.
+    pub fn play_stems(&mut self, frames: &mut [StemFrame]) -> GmeResult<()> {
+        if !self.is_multi_channel() {
+            return Err(GmeError::with_kind(
+                GmeErrorKind::Unsupported,
+                "Stems require an emulator created with new_multi_channel",
+            ));
+        }
+        self.play(frames.as_flattened_mut().as_flattened_mut())
+    }
+
+    /// Names of the voices rendered into each stem by [Self::play_stems]. Stems past the voice
+    /// count are empty.
+    pub fn stem_labels(&self) -> [Vec<String>; STEM_COUNT] {
+        stem_labels(self.voice_names())
+    }
+
+    /// Fill `frames` with stereo frames, where each frame is a `[left, right]` pair
+    pub fn render_frames(&mut self, frames: &mut [[i16; 2]]) -> GmeResult<()> {
+        self.play(frames.as_flattened_mut())
     }
 
     /// Start a track, where 0 is the first track
-    pub fn start_track(&self, index: usize) -> GmeResult<()> {
-        native::start_track(&self.handle, index as _)
+    pub fn start_track(&mut self, index: usize) -> GmeResult<()> {
+        let limit = self.playback_limit;
+        native::set_autoload_playback_limit(&mut self.handle, limit == PlaybackLimit::Auto);
+        let ignore_silence = self.ignore_silence || limit == PlaybackLimit::Infinite;
+        native::ignore_silence(&mut self.handle, ignore_silence);
+
+        let result = native::start_track(&mut self.handle, index as _);
+        self.record_warning();
+        // A track that fails to start, such as one that doesn't exist, leaves the last one
+        if result.is_ok() {
+            self.current_track = Some(index);
+            self.apply_playback_limit();
+            self.fade_in.restart();
+            self.reset_resampler();
+        }
+        result
     }
 
-    /// Number of milliseconds played since beginning of track
-    pub fn tell(&self) -> u32 {
-        native::tell(&self.handle)
+    /// The track started by the last successful call to [Self::start_track]
+    pub fn current_track(&self) -> Option<usize> {
+        self.current_track
+    }
+
+    /// Most recent emulation warning, such as an illegal instruction or unsupported hardware, or
+    /// `None` if there is none. Clears the warning. Loading a file or starting a track also
+    /// clears it, unless warnings are being collected.
+    pub fn take_warning(&mut self) -> Option<String> {
+        match &mut self.warnings {
+            Some(warnings) => {
+                warnings.extend(native::warning(&mut self.handle));
+                warnings.pop()
+            }
+            None => native::warning(&mut self.handle),
+        }
+    }
+
+    /// When enabled, every warning raised while loading, starting tracks, seeking and playing is
+    /// kept until it is retrieved with [Self::take_warnings], instead of only the most recent one.
+    /// Disabling it discards any warnings that were collected.
+    pub fn collect_warnings(&mut self, collect: bool) {
+        self.warnings = collect.then(Vec::new);
+    }
+
+    /// All warnings collected since the last call, oldest first. Repeats of the same warning in a
+    /// row are recorded once. Only returns the current warning unless collecting is enabled
+    /// with [Self::collect_warnings].
+    pub fn take_warnings(&mut self) -> Vec<String> {
+        self.record_warning();
+        match &mut self.warnings {
+            Some(warnings) => std::mem::take(warnings),
+            None => native::warning(&mut self.handle).into_iter().collect(),
+        }
+    }
+
+    /// Moves the current warning into the log if collecting is enabled
+    fn record_warning(&mut self) {
+        if let Some(warnings) = &mut self.warnings
+            && let Some(warning) = native::warning(&mut self.handle)
+            && warnings.last() != Some(&warning)
+        {
+            warnings.push(warning);
+        }
+    }
+
+    /// Time played since beginning of track
+    pub fn tell(&self) -> Duration {
+        Duration::from_millis(native::tell(&self.handle) as u64)
+            .saturating_sub(self.resampler_delay())
+    }
+
+    /// Number of frames played since beginning of track, at [Self::emulator_sample_rate]
+    pub fn tell_samples(&self) -> SamplePosition {
+        let channels = native::out_channels(&self.handle) as u32;
+        let buffered = self
+            .resampler
+            .as_ref()
+            .map_or(0, |resampler| resampler.buffered_frames() as u32);
+        let frames = native::tell_samples(&self.handle) / channels;
+        SamplePosition(frames.saturating_sub(buffered))
+    }
+
+    /// Time played since beginning of track, scaled with tempo. At a tempo of 2.0, this advances
+    /// twice as fast as [Self::tell].
+    pub fn tell_scaled(&self) -> Duration {
+        Duration::from_millis(native::tell_scaled(&self.handle) as u64)
+            .saturating_sub(self.resampler_delay().mul_f64(self.tempo))
+    }
+
+    /// The [crate::EmuType] of this emulator
+    pub fn emu_type(&self) -> EmuType {
+        native::emu_type(&self.handle)
     }
 
     /// Number of tracks available
@@ -66,57 +409,211 @@ impl GameMusicEmu {
         native::track_count(&self.handle)
     }
 
-    /// True if track ended
+    /// True if track ended. When that happens depends on the [PlaybackLimit].
     pub fn track_ended(&self) -> bool {
         native::track_ended(&self.handle)
     }
 
-    pub fn seek(&self, msec: u32) -> GmeResult<()> {
-        native::seek(&self.handle, msec)
+    /// Seek to a time in the track. Seeking backwards or far forward can take a while.
+    pub fn seek(&mut self, position: Duration) -> GmeResult<()> {
+        let result = native::seek(&mut self.handle, to_msec(position));
+        self.after_seek();
+        result
     }
 
-    pub fn set_fade(&self, start_msec: u32) {
-        native::set_fade(&self.handle, start_msec)
+    /// Seek to an exact frame in the track. Equivalent to restarting the track and skipping
+    /// `position` frames.
+    pub fn seek_samples(&mut self, position: SamplePosition) -> GmeResult<()> {
+        let samples = position.samples(native::out_channels(&self.handle) as u32);
+        let result = native::seek_samples(&mut self.handle, samples.min(i32::MAX as u32));
+        self.after_seek();
+        result
     }
 
-    pub fn set_stereo_depth(&self, depth: f64) {
-        native::set_stereo_depth(&self.handle, depth)
+    /// Seek to a time in the track, scaled with tempo like [Self::tell_scaled]
+    pub fn seek_scaled(&mut self, position: Duration) -> GmeResult<()> {
+        let result = native::seek_scaled(&mut self.handle, to_msec(position));
+        self.after_seek();
+        result
     }
 
-    pub fn ignore_silence(&self, ignore: bool) {
-        native::ignore_silence(&self.handle, ignore)
+    fn after_seek(&mut self) {
+        self.record_warning();
+        // Seeking backwards restarts the track, which clears the fade
+        self.apply_playback_limit();
+        self.fade_in.restart();
+        self.reset_resampler();
     }
 
-    pub fn set_tempo(&self, tempo: f64) {
-        native::set_tempo(&self.handle, tempo)
+    /// Set how long tracks play before they end. Takes effect immediately, except that
+    /// switching to [PlaybackLimit::Auto] only applies from the next [Self::start_track].
+    pub fn set_playback_limit(&mut self, limit: PlaybackLimit) {
+        self.playback_limit = limit;
+        let ignore_silence = self.ignore_silence || limit == PlaybackLimit::Infinite;
+        native::ignore_silence(&mut self.handle, ignore_silence);
+        self.apply_playback_limit();
     }
 
-    pub fn mute_voice(&self, voice: u32, mute: bool) {
-        native::mute_voice(&self.handle, voice, mute)
+    pub fn playback_limit(&self) -> PlaybackLimit {
+        self.playback_limit
     }
 
-    pub fn mute_voices(&self, mask: i32) {
-        native::mute_voices(&self.handle, mask)
+    /// Set how long the fade out at the end of a [PlaybackLimit] lasts. The default is
+    /// [DEFAULT_FADE_LENGTH].
+    pub fn set_playback_fade_length(&mut self, length: Duration) {
+        self.playback_fade_length = length;
+        self.apply_playback_limit();
+    }
+
+    pub fn playback_fade_length(&self) -> Duration {
+        self.playback_fade_length
+    }
+
+    /// Sets the fade for the playback limit of the current track
+    fn apply_playback_limit(&mut self) {
+        let Some(track) = self.current_track else {
+            return;
+        };
+        match self.playback_limit {
+            PlaybackLimit::Auto => {}
+            PlaybackLimit::Infinite => self.disable_fade(),
+            limit => {
+                let fade_start = self
+                    .track_info(track as u32)
+                    .ok()
+                    .and_then(|info| limit.fade_start(&info));
+                match fade_start {
+                    Some(start) => self.set_fade(start, self.playback_fade_length),
+                    None => self.disable_fade(),
+                }
+            }
+        }
+    }
+
+    /// Start fading the track out at `start`, taking `length` to fade to silence. Once the fade
+    /// ends, [Self::track_ended] returns true. Can be changed while the track is playing.
+    /// Starting a track clears the fade. Very short lengths are raised to the shortest fade Game
+    /// Music Emu supports at the current sample rate.
+    pub fn set_fade(&mut self, start: Duration, length: Duration) {
+        // Game Music Emu divides by a step that is 0 for fades shorter than this
+        let min_length_msec = 2_048_000_u32.div_ceil(self.sample_rate.max(1));
+        let length_msec = to_msec(length).max(min_length_msec);
+        native::set_fade_msecs(&mut self.handle, to_msec(start) as i32, length_msec as i32)
+    }
+
+    /// Stop the current track from fading out, so it plays until it ends on its own. Call this
+    /// after [Self::start_track], since some emulators set a fade when a track starts.
+    pub fn disable_fade(&mut self) {
+        native::set_fade_msecs(&mut self.handle, -1, DEFAULT_FADE_LENGTH.as_millis() as i32)
+    }
+
+    /// Fade in from silence over `length` whenever a track starts or the position changes with
+    /// a seek. [Duration::ZERO] turns fading in off, which is the default.
+    pub fn set_fade_in(&mut self, length: Duration) {
+        let frames = SamplePosition::from_duration(length, self.sample_rate).frames();
+        self.fade_in = FadeIn::new(frames);
+    }
+
+    pub fn set_stereo_depth(&mut self, depth: f64) {
+        native::set_stereo_depth(&mut self.handle, depth)
+    }
+
+    /// Disable automatic end-of-track detection and skipping of silence at the beginning of
+    /// tracks. Always disabled while the [PlaybackLimit] is [PlaybackLimit::Infinite].
+    pub fn ignore_silence(&mut self, ignore: bool) {
+        self.ignore_silence = ignore;
+        let ignore = ignore || self.playback_limit == PlaybackLimit::Infinite;
+        native::ignore_silence(&mut self.handle, ignore)
+    }
+
+    pub fn set_tempo(&mut self, tempo: f64) {
+        // The same limits as Game Music Emu
+        self.tempo = tempo.clamp(0.02, 4.0);
+        native::set_tempo(&mut self.handle, tempo)
+    }
+
+    /// Mute or unmute a voice. Fails with [GmeErrorKind::InvalidArgument] if `voice` is not
+    /// less than [Self::voice_count].
+    pub fn mute_voice(&mut self, voice: u32, mute: bool) -> GmeResult<()> {
+        self.check_voice(voice)?;
+        native::mute_voice(&mut self.handle, voice, mute);
+        self.muted_voices.set(voice, mute);
+        Ok(())
+    }
+
+    /// Mute exactly the voices in `mask` and unmute the rest
+    pub fn mute_voices(&mut self, mask: VoiceMask) {
+        native::mute_voices(&mut self.handle, mask.bits() as i32);
+        self.muted_voices = mask;
+    }
+
+    /// Voices that are currently muted. Muting persists when another file is loaded.
+    pub fn muted_voices(&self) -> VoiceMask {
+        self.muted_voices
+    }
+
+    pub fn is_voice_muted(&self, voice: u32) -> bool {
+        self.muted_voices.contains(voice)
+    }
+
+    /// Mute every voice except `voice`
+    pub fn solo(&mut self, voice: u32) -> GmeResult<()> {
+        self.check_voice(voice)?;
+        self.mute_voices(VoiceMask::all(self.voice_count()) - VoiceMask::single(voice));
+        Ok(())
+    }
+
+    pub fn unmute_all(&mut self) {
+        self.mute_voices(VoiceMask::empty())
+    }
+
+    fn check_voice(&self, voice: u32) -> GmeResult<()> {
+        let count = self.voice_count();
+        if voice >= count {
+            return Err(GmeError::with_kind(
+                GmeErrorKind::InvalidArgument,
+                format!("Voice {} is out of range for {} voices", voice, count),
+            ));
+        }
+        Ok(())
     }
 
     pub fn voice_count(&self) -> u32 {
         native::voice_count(&self.handle)
     }
 
+    /// Name of a voice, or `None` if `index` is not less than [Self::voice_count]
     pub fn voice_name(&self, index: u32) -> Option<String> {
-        native::voice_name(&self.handle, index)
+        (index < self.voice_count())
+            .then(|| native::voice_name(&self.handle, index))
+            .flatten()
+    }
+
+    /// Names of all voices, in index order
+    pub fn voice_names(&self) -> Vec<String> {
+        (0..self.voice_count())
+            .filter_map(|index| self.voice_name(index))
+            .collect()
+    }
+
+    /// Index of the voice called `name`, ignoring case. For example, `"triangle"` on NES.
+    pub fn voice_index(&self, name: &str) -> Option<u32> {
+        (0..self.voice_count()).find(|&index| {
+            self.voice_name(index)
+                .is_some_and(|voice_name| voice_name.eq_ignore_ascii_case(name))
+        })
     }
 
-    pub fn load_m3u(&self, path: impl AsRef<Path>) -> GmeResult<()> {
-        native::load_m3u(&self.handle, path)
+    pub fn load_m3u(&mut self, path: impl AsRef<Path>) -> GmeResult<()> {
+        native::load_m3u(&mut self.handle, path)
     }
 
-    pub fn load_m3u_data(&self, data: impl AsRef<[u8]>) -> GmeResult<()> {
-        native::load_m3u_data(&self.handle, data.as_ref())
+    pub fn load_m3u_data(&mut self, data: impl AsRef<[u8]>) -> GmeResult<()> {
+        native::load_m3u_data(&mut self.handle, data.as_ref())
     }
 
-    pub fn clear_playlist(&self) {
-        native::clear_playlist(&self.handle);
+    pub fn clear_playlist(&mut self) {
+        native::clear_playlist(&mut self.handle);
     }
 
     pub fn track_info(&self, track: u32) -> GmeResult<EmuTrackInfo> {
@@ -127,27 +624,85 @@ impl GameMusicEmu {
         native::equalizer(&self.handle)
     }
 
-    pub fn set_equalizer(&self, eq: EmuEqualizer) -> GmeResult<()> {
-        native::set_equalizer(&self.handle, eq)
+    /// Fails with [GmeErrorKind::InvalidArgument] if the treble or bass is out of range. See
+    /// [crate::EqualizerPreset] for settings that match particular hardware.
+    pub fn set_equalizer(&mut self, eq: impl Into<EmuEqualizer>) -> GmeResult<()> {
+        native::set_equalizer(&mut self.handle, eq.into())
+    }
+
+    pub fn enable_accuracy(&mut self, enable: bool) {
+        native::enable_accuracy(&mut self.handle, enable)
     }
 
-    pub fn enable_accuracy(&self, enable: bool) {
-        native::enable_accuracy(&self.handle, enable)
+    /// Disable the echo effect. Currently only the SPC emulator has one; other types ignore
+    /// this.
+    pub fn disable_echo(&mut self, disable: bool) {
+        native::disable_echo(&mut self.handle, disable)
     }
+
+    /// Settings specific to the SPC emulator, or `None` if this isn't an SPC emulator
+    #[cfg(feature = "spc")]
+    pub fn spc_options(&mut self) -> Option<SpcOptions<'_>> {
+        (self.emu_type() == EmuType::Spc).then(|| SpcOptions::new(&mut self.handle))
+    }
+}
+
+/// Samples [GameMusicEmu::play_f32] generates at a time, in a buffer on the stack. A multiple of
+/// 16 so chunks hold whole frames in multi-channel mode too.
+const F32_CHUNK_LEN: usize = 2048;
+
+/// How long a fade out lasts if the file doesn't say
+pub const DEFAULT_FADE_LENGTH: Duration = Duration::from_secs(8);
+
+/// Sample rates an emulator can be created with. Game Music Emu's buffers fail outside of a
+/// range like this, and crash the process at a rate of 0.
+pub const SAMPLE_RATES: RangeInclusive<u32> = 8000..=384_000;
+
+fn check_sample_rate(sample_rate: u32) -> GmeResult<()> {
+    if !SAMPLE_RATES.contains(&sample_rate) {
+        return Err(GmeError::with_kind(
+            GmeErrorKind::InvalidArgument,
+            format!(
+                "Sample rate {} is outside the supported range of {} to {}",
+                sample_rate,
+                SAMPLE_RATES.start(),
+                SAMPLE_RATES.end()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+/// Converts a duration to the milliseconds Game Music Emu takes, which must fit in an `int`
+fn to_msec(duration: Duration) -> u32 {
+    duration.as_millis().min(i32::MAX as u128) as u32
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::EqualizerPreset;
     use crate::test_utils::*;
-    use std::sync::Arc;
 
     #[test]
     fn test_new_emu() {
-        let emu = GameMusicEmu::new(EmuType::Nsf, 44100);
+        let emu = GameMusicEmu::new(EmuType::Nsf, 44100).unwrap();
         assert!(!emu.handle.to_raw().is_null());
     }
 
+    #[test]
+    fn test_sample_rate_out_of_range() {
+        for rate in [0, 1000, 1_000_000] {
+            let result = GameMusicEmu::new(EmuType::Nsf, rate);
+            assert!(matches!(result, Err(err) if err.kind() == GmeErrorKind::InvalidArgument));
+            let result = GameMusicEmu::from_file(TEST_NSF_PATH, rate);
+            assert!(matches!(
+                result,
+                Err(GmeOrIoError::Gme(err)) if err.kind() == GmeErrorKind::InvalidArgument
+            ));
+        }
+    }
+
     #[test]
     fn test_from_file() {
         let emu = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
@@ -156,6 +711,21 @@ mod tests {
         assert_eq!(emu.voice_name(0).as_deref(), Some("Square 1"));
     }
 
+    #[test]
+    fn test_start_invalid_track() {
+        let mut emu = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        let err = emu.start_track(5).unwrap_err();
+        assert_eq!(err.kind(), GmeErrorKind::InvalidTrack);
+    }
+
+    #[test]
+    fn test_emu_type() {
+        let emu = GameMusicEmu::new(EmuType::Spc, 44100).unwrap();
+        assert_eq!(emu.emu_type(), EmuType::Spc);
+        let emu = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        assert_eq!(emu.emu_type(), EmuType::Nsf);
+    }
+
     #[test]
     fn test_from_data() {
         let data = get_test_nsf_data();
@@ -168,12 +738,10 @@ mod tests {
     #[test]
     fn test_load_data() {
         let buffer = get_test_nsf_data();
-        let emulator = GameMusicEmu::new(EmuType::Nsf, 44100);
-        let result = emulator.load_data(vec![1_u8, 2, 3]);
-        assert_eq!(
-            result.err().unwrap().message(),
-            "Wrong file type for this emulator"
-        );
+        let mut emulator = GameMusicEmu::new(EmuType::Nsf, 44100).unwrap();
+        let err = emulator.load_data(vec![1_u8, 2, 3]).unwrap_err();
+        assert_eq!(err.kind(), GmeErrorKind::WrongFileType);
+        assert_eq!(err.message(), "Wrong file type for this emulator");
         assert_eq!(emulator.track_count(), 0);
         assert_eq!(emulator.voice_count(), 0);
         emulator.load_data(&buffer).unwrap();
@@ -183,25 +751,393 @@ mod tests {
     }
 
     #[test]
-    fn test_arc() {
-        let gme = GameMusicEmu::new(EmuType::Nsf, 44100);
-        let handle = gme.handle;
-        assert_eq!(Arc::strong_count(&handle.emu), 1);
-        let handle = handle.clone();
-        assert_eq!(Arc::strong_count(&handle.emu), 2);
+    fn test_load_reader() {
+        let mut emulator = GameMusicEmu::new(EmuType::Nsf, 44100).unwrap();
+        emulator
+            .load_reader(std::io::Cursor::new(get_test_nsf_data()))
+            .unwrap();
+        assert_eq!(emulator.track_count(), 1);
+        emulator.start_track(0).unwrap();
+        let mut buffer = [0_i16; 2048];
+        emulator.play(&mut buffer).unwrap();
+        assert!(buffer.iter().any(|&sample| sample != 0));
+    }
+
+    #[test]
+    fn test_load_reader_with_size() {
+        let data = get_test_nsf_data();
+        let mut emulator = GameMusicEmu::new(EmuType::Nsf, 44100).unwrap();
+        // Slices can't seek
+        emulator
+            .load_reader_with_size(&data[..], data.len() as u64)
+            .unwrap();
+        assert_eq!(emulator.track_count(), 1);
+
+        let size = data.len() as u64 + 1;
+        match emulator.load_reader_with_size(&data[..], size).unwrap_err() {
+            GmeOrIoError::IoError(err) => {
+                assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof)
+            }
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn test_load_reader_io_error() {
+        /// Fails every read, but reports the size of the test file
+        struct FailingReader(std::io::Cursor<Vec<u8>>);
+
+        impl Read for FailingReader {
+            fn read(&mut self, _buf: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::Error::other("unreachable storage"))
+            }
+        }
+
+        impl Seek for FailingReader {
+            fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+                self.0.seek(pos)
+            }
+        }
+
+        let mut emulator = GameMusicEmu::new(EmuType::Nsf, 44100).unwrap();
+        let reader = FailingReader(std::io::Cursor::new(get_test_nsf_data()));
+        match emulator.load_reader(reader).unwrap_err() {
+            GmeOrIoError::IoError(err) => assert_eq!(err.to_string(), "unreachable storage"),
+            err => panic!("unexpected error: {err}"),
+        }
+    }
+
+    #[test]
+    fn test_load_tracks_unsupported_type() {
+        let mut emulator = GameMusicEmu::new(EmuType::Nsf, 44100).unwrap();
+        let err = emulator.load_tracks(&[get_test_nsf_data()]).unwrap_err();
+        assert_eq!(err.kind(), GmeErrorKind::Unsupported);
+        let err = emulator.load_tracks(&[] as &[&[u8]]).unwrap_err();
+        assert_eq!(err.kind(), GmeErrorKind::InvalidArgument);
+    }
+
+    #[test]
+    #[cfg(feature = "gym")]
+    fn test_load_tracks() {
+        /// A GYM file with an empty header where every command waits one frame (1/60 s)
+        fn gym_file(frames: usize) -> Vec<u8> {
+            let mut data = b"GYMX".to_vec();
+            data.resize(428 + frames, 0);
+            data
+        }
+
+        let tracks = [gym_file(60), gym_file(120), gym_file(180)];
+        let mut emulator = GameMusicEmu::new(EmuType::Gym, 44100).unwrap();
+        emulator.load_tracks(&tracks).unwrap();
+        assert_eq!(emulator.track_count(), 3);
+        for (index, seconds) in [(0, 1), (1, 2), (2, 3)] {
+            let info = emulator.track_info(index).unwrap();
+            assert_eq!(info.length, Some(Duration::from_secs(seconds)));
+        }
+        // Each track plays its own file, so the last one ends after 3 seconds
+        emulator.ignore_silence(true);
+        emulator.start_track(2).unwrap();
+        let played = play_until_ended(&mut emulator, Duration::from_secs(10));
+        assert!(played >= Duration::from_secs(3) && played < Duration::from_secs(4));
+    }
+
+    #[test]
+    fn test_mute_voices() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        assert_eq!(gme.voice_index("triangle"), Some(2));
+        assert_eq!(gme.voice_index("Kazoo"), None);
+        assert_eq!(gme.voice_name(5), None);
+
+        gme.mute_voice(1, true).unwrap();
+        assert!(gme.is_voice_muted(1));
+        let err = gme.mute_voice(5, true).unwrap_err();
+        assert_eq!(err.kind(), GmeErrorKind::InvalidArgument);
+
+        gme.solo(2).unwrap();
+        assert!(!gme.is_voice_muted(2));
+        assert!(gme.is_voice_muted(0) && gme.is_voice_muted(4));
+        assert_eq!(gme.muted_voices().iter().collect::<Vec<_>>(), [0, 1, 3, 4]);
+        assert!(gme.solo(9).is_err());
+
+        gme.unmute_all();
+        assert_eq!(gme.muted_voices(), VoiceMask::empty());
+    }
+
+    #[test]
+    fn test_muted_voices_are_silent() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.mute_voices(VoiceMask::all(gme.voice_count()));
+        gme.start_track(0).unwrap();
+        let mut buffer = [0_i16; 4096];
+        gme.play(&mut buffer).unwrap();
+        assert!(buffer.iter().all(|&sample| sample.abs() < 64));
+    }
+
+    #[test]
+    fn test_send() {
+        fn assert_send<T: Send>() {}
+        assert_send::<GameMusicEmu>();
+
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.start_track(0).unwrap();
+        let gme = std::thread::spawn(move || {
+            let mut buffer = [0_i16; 512];
+            gme.play(&mut buffer).unwrap();
+            gme
+        })
+        .join()
+        .unwrap();
+        assert!(gme.tell() > Duration::ZERO);
     }
 
     #[test]
     fn test_seek_and_tell() {
-        let gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
         gme.start_track(0).unwrap();
-        gme.seek(10000).unwrap();
-        assert!(gme.tell() >= 10000);
+        gme.seek(Duration::from_secs(10)).unwrap();
+        assert!(gme.tell() >= Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_seek_and_tell_samples() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.start_track(0).unwrap();
+        let position = SamplePosition::from_frames(12345);
+        gme.seek_samples(position).unwrap();
+        assert_eq!(gme.tell_samples(), position);
+        gme.seek_samples(SamplePosition::ZERO).unwrap();
+        assert_eq!(gme.tell_samples(), SamplePosition::ZERO);
+    }
+
+    #[test]
+    fn test_seek_and_tell_scaled() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.set_tempo(2.0);
+        gme.start_track(0).unwrap();
+        gme.seek_scaled(Duration::from_secs(4)).unwrap();
+        assert!(gme.tell_scaled() >= Duration::from_millis(3990));
+        assert!(gme.tell() >= Duration::from_millis(1990));
+        assert!(gme.tell() <= Duration::from_millis(2010));
+    }
+
+    #[test]
+    fn test_play() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.start_track(0).unwrap();
+        let mut buffer = [0_i16; 2048];
+        gme.play(&mut buffer).unwrap();
+        assert!(buffer.iter().any(|&sample| sample != 0));
+    }
+
+    #[test]
+    fn test_play_odd_length() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.start_track(0).unwrap();
+        let mut buffer = [0_i16; 7];
+        let err = gme.play(&mut buffer).unwrap_err();
+        assert_eq!(err.kind(), GmeErrorKind::InvalidBufferLength);
+    }
+
+    #[test]
+    fn test_play_f32() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.start_track(0).unwrap();
+        let mut reference = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        reference.start_track(0).unwrap();
+
+        // Longer than one chunk, to cover the chunking
+        let mut buffer = vec![0_f32; 5000];
+        gme.set_gain(0.5);
+        gme.play_f32(&mut buffer).unwrap();
+        let mut samples = vec![0_i16; 5000];
+        reference.play(&mut samples).unwrap();
+        for (&float, &sample) in buffer.iter().zip(&samples) {
+            assert_eq!(float, sample as f32 / 32768.0 * 0.5);
+        }
+        assert!(buffer.iter().any(|&sample| sample != 0.0));
+
+        let err = gme.play_f32(&mut [0.0; 3]).unwrap_err();
+        assert_eq!(err.kind(), GmeErrorKind::InvalidBufferLength);
+    }
+
+    #[test]
+    fn test_render_frames() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.start_track(0).unwrap();
+        let mut frames = [[0_i16; 2]; 1024];
+        gme.render_frames(&mut frames).unwrap();
+        assert!(frames.iter().flatten().any(|&sample| sample != 0));
+        assert!(gme.tell() > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_play_stems() {
+        let mut gme = GameMusicEmu::new_multi_channel(EmuType::Nsf, 44100).unwrap();
+        assert!(gme.is_multi_channel());
+        gme.load_file(TEST_NSF_PATH).unwrap();
+        gme.start_track(0).unwrap();
+        let mut frames = [[[0_i16; 2]; STEM_COUNT]; 1024];
+        gme.play_stems(&mut frames).unwrap();
+        assert!(frames.iter().flatten().flatten().any(|&sample| sample != 0));
+        assert_eq!(gme.stem_labels()[0], ["Square 1"]);
+        assert_eq!(gme.tell_samples(), SamplePosition::from_frames(1024));
+        assert_eq!(
+            gme.tell_samples().to_duration(44100),
+            Duration::from_nanos(1024 * 1_000_000_000 / 44100)
+        );
+
+        let mut buffer = [0_i16; 8];
+        let err = gme.play(&mut buffer).unwrap_err();
+        assert_eq!(err.kind(), GmeErrorKind::InvalidBufferLength);
+    }
+
+    #[test]
+    fn test_play_stems_requires_multi_channel() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        assert!(!gme.is_multi_channel());
+        let mut frames = [[[0_i16; 2]; STEM_COUNT]; 16];
+        let err = gme.play_stems(&mut frames).unwrap_err();
+        assert_eq!(err.kind(), GmeErrorKind::Unsupported);
+    }
+
+    #[test]
+    #[cfg(feature = "spc")]
+    fn test_new_multi_channel_unsupported() {
+        let err = GameMusicEmu::new_multi_channel(EmuType::Spc, 44100)
+            .err()
+            .unwrap();
+        assert_eq!(err.kind(), GmeErrorKind::Unsupported);
+    }
+
+    /// Plays until the track ends or `limit` is reached. Returns the time played.
+    fn play_until_ended(gme: &mut GameMusicEmu, limit: Duration) -> Duration {
+        let mut buffer = [0_i16; 4410];
+        while !gme.track_ended() && gme.tell() < limit {
+            gme.play(&mut buffer).unwrap();
+        }
+        gme.tell()
+    }
+
+    #[test]
+    fn test_set_fade() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.start_track(0).unwrap();
+        gme.set_fade(Duration::from_secs(1), Duration::from_secs(1));
+        let played = play_until_ended(&mut gme, Duration::from_secs(10));
+        assert!(played >= Duration::from_secs(2) && played < Duration::from_millis(2200));
+    }
+
+    #[test]
+    fn test_set_fade_short_length() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 8000).unwrap();
+        gme.start_track(0).unwrap();
+        gme.set_fade(Duration::from_millis(500), Duration::ZERO);
+        let played = play_until_ended(&mut gme, Duration::from_secs(10));
+        assert!(played < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_disable_fade() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.start_track(0).unwrap();
+        gme.set_fade(Duration::from_secs(1), Duration::from_secs(1));
+        gme.disable_fade();
+        let played = play_until_ended(&mut gme, Duration::from_secs(5));
+        assert!(played >= Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_disable_echo() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.disable_echo(true);
+        gme.start_track(0).unwrap();
+        let mut buffer = [0i16; 1024];
+        gme.play(&mut buffer).unwrap();
+    }
+
+    #[test]
+    #[cfg(feature = "spc")]
+    fn test_spc_options() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        assert!(gme.spc_options().is_none());
+
+        let mut gme = GameMusicEmu::new(EmuType::Spc, 44100).unwrap();
+        gme.spc_options()
+            .unwrap()
+            .disable_surround(true)
+            .disable_echo(true)
+            .enable_filter(true);
+    }
+
+    #[test]
+    fn test_failed_start_keeps_current_track() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.start_track(0).unwrap();
+        assert!(gme.start_track(99).is_err());
+        assert_eq!(gme.current_track(), Some(0));
+        // The limit still applies to the track that is playing
+        gme.set_playback_limit(PlaybackLimit::Fixed(Duration::from_secs(1)));
+        let played = play_until_ended(&mut gme, Duration::from_secs(20));
+        assert!(played < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_playback_limit_fixed() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.set_playback_limit(PlaybackLimit::Fixed(Duration::from_secs(1)));
+        gme.start_track(0).unwrap();
+        let played = play_until_ended(&mut gme, Duration::from_secs(20));
+        assert!(played >= Duration::from_secs(9) && played < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_playback_limit_survives_seek() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.set_playback_limit(PlaybackLimit::Fixed(Duration::from_secs(1)));
+        gme.start_track(0).unwrap();
+        gme.seek(Duration::from_secs(3)).unwrap();
+        gme.seek(Duration::ZERO).unwrap();
+        let played = play_until_ended(&mut gme, Duration::from_secs(20));
+        assert!(played < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn test_playback_limit_infinite() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.start_track(0).unwrap();
+        gme.set_fade(Duration::from_secs(1), Duration::from_secs(1));
+        gme.set_playback_limit(PlaybackLimit::Infinite);
+        let played = play_until_ended(&mut gme, Duration::from_secs(5));
+        assert!(played >= Duration::from_secs(5));
+        assert_eq!(gme.playback_limit(), PlaybackLimit::Infinite);
+    }
+
+    #[test]
+    fn test_fade_in() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        let mut faded = [[0_i16; 2]; 4410];
+        gme.set_fade_in(Duration::from_millis(100));
+        gme.start_track(0).unwrap();
+        gme.render_frames(&mut faded).unwrap();
+
+        let mut unfaded = [[0_i16; 2]; 4410];
+        gme.set_fade_in(Duration::ZERO);
+        gme.start_track(0).unwrap();
+        gme.render_frames(&mut unfaded).unwrap();
+
+        let level = |frames: &[[i16; 2]]| -> u64 {
+            frames
+                .iter()
+                .flatten()
+                .map(|s| s.unsigned_abs() as u64)
+                .sum()
+        };
+        assert!(level(&faded[..2205]) < level(&unfaded[..2205]));
     }
 
     #[test]
     fn test_load_m3u() {
-        let emu = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        let mut emu = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
         assert_eq!(emu.track_count(), 1);
         emu.load_m3u(TEST_M3U_PATH).unwrap();
         emu.clear_playlist();
@@ -209,7 +1145,7 @@ mod tests {
 
     #[test]
     fn test_load_m3u_data() {
-        let emu = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        let mut emu = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
         assert_eq!(emu.track_count(), 1);
         emu.load_m3u_data(get_test_m3u_data()).unwrap();
         emu.clear_playlist();
@@ -222,19 +1158,61 @@ mod tests {
         assert_eq!(info.length, None);
         assert_eq!(info.intro_length, None);
         assert_eq!(info.loop_length, None);
-        assert_eq!(info.play_length, 150000);
+        assert_eq!(info.fade_length, None);
+        assert_eq!(info.play_length, Duration::from_secs(150));
         assert_eq!(info.system, Some("Nintendo NES".into()));
         assert_eq!(info.game, Some("Tetris (GB)".into()));
-        assert_eq!(info.song, Some("".into()));
-        assert_eq!(info.author, Some("".into()));
+        assert_eq!(info.song, None);
+        assert_eq!(info.author, None);
         assert_eq!(info.copyright, Some("Nintendo".into()));
-        assert_eq!(info.comment, Some("".into()));
-        assert_eq!(info.dumper, Some("".into()));
+        assert_eq!(info.comment, None);
+        assert_eq!(info.dumper, None);
+        assert_eq!(info.to_string(), "Tetris (GB) (02:30)");
+    }
+
+    /// Test data with an unknown NSF version, which Game Music Emu warns about
+    fn get_unknown_version_nsf_data() -> Vec<u8> {
+        let mut data = get_test_nsf_data();
+        data[5] = 2;
+        data
+    }
+
+    /// Test data whose play routine starts on an illegal instruction
+    fn get_illegal_instruction_nsf_data() -> Vec<u8> {
+        let mut data = get_test_nsf_data();
+        let offset = data[0x80..].iter().position(|&byte| byte == 0xFF).unwrap();
+        let address = (0x8000 + offset) as u16;
+        data[0x0C..0x0E].copy_from_slice(&address.to_le_bytes());
+        data
+    }
+
+    #[test]
+    fn test_take_warning() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        assert_eq!(gme.take_warning(), None);
+        gme.load_data(get_unknown_version_nsf_data()).unwrap();
+        assert_eq!(gme.take_warning().as_deref(), Some("Unknown file version"));
+        assert_eq!(gme.take_warning(), None);
+    }
+
+    #[test]
+    fn test_collect_warnings() {
+        let mut gme = GameMusicEmu::new(EmuType::Nsf, 44100).unwrap();
+        gme.collect_warnings(true);
+        gme.load_data(get_illegal_instruction_nsf_data()).unwrap();
+        gme.start_track(0).unwrap();
+        let mut buffer = [0_i16; 4096];
+        for _ in 0..10 {
+            gme.play(&mut buffer).unwrap();
+        }
+        let warnings = gme.take_warnings();
+        assert_eq!(warnings, ["Emulation error (illegal instruction)"]);
+        assert!(gme.take_warnings().is_empty());
     }
 
     #[test]
     fn test_equalizer() {
-        let gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
         let eq1 = gme.equalizer();
         assert_eq!(eq1.bass, 80.0);
         let mut eq2 = eq1.clone();
@@ -242,5 +1220,50 @@ mod tests {
         gme.set_equalizer(eq2).unwrap();
         let eq3 = gme.equalizer();
         assert_eq!(eq3.bass, 30.0);
+
+        gme.set_equalizer(EqualizerPreset::Tv).unwrap();
+        assert_eq!(gme.equalizer(), EmuEqualizer::new(-8.0, 180.0));
+        let err = gme
+            .set_equalizer(EmuEqualizer::new(0.0, 20000.0))
+            .unwrap_err();
+        assert_eq!(err.kind(), GmeErrorKind::InvalidArgument);
+        assert_eq!(gme.equalizer(), EmuEqualizer::new(-8.0, 180.0));
+    }
+
+    #[test]
+    fn test_set_sample_rate() {
+        let mut gme = GameMusicEmu::from_file(TEST_NSF_PATH, 44100).unwrap();
+        gme.start_track(0).unwrap();
+        let mut buffer = vec![0_i16; 88200];
+        gme.play(&mut buffer).unwrap();
+
+        gme.set_sample_rate(48000).unwrap();
+        assert_eq!(gme.sample_rate(), 48000);
+        assert_eq!(gme.emulator_sample_rate(), 44100);
+        let mut buffer = vec![0_i16; 96000];
+        gme.play(&mut buffer).unwrap();
+        assert!(buffer.iter().any(|&sample| sample != 0));
+        let position = gme.tell().as_millis();
+        assert!(position.abs_diff(2000) <= 2, "{position}");
+        let frames = gme.tell_samples().frames();
+        assert!(frames.abs_diff(88200) <= 50, "{frames}");
+
+        // Returning to the emulator's rate stops resampling on the next seek
+        gme.set_sample_rate(44100).unwrap();
+        gme.seek(Duration::from_secs(1)).unwrap();
+        assert!(gme.resampler.is_none());
+        assert_eq!(gme.tell(), Duration::from_secs(1));
+
+        for rate in [0, 1, 5000, 400000] {
+            let err = gme.set_sample_rate(rate).unwrap_err();
+            assert_eq!(err.kind(), GmeErrorKind::InvalidArgument);
+        }
+        gme.set_sample_rate(44100 * 8).unwrap();
+        gme.set_sample_rate(44100 / 8 + 1).unwrap();
+        assert_eq!(gme.sample_rate(), 5513);
+        let mut gme = GameMusicEmu::new_multi_channel(EmuType::Nsf, 44100).unwrap();
+        let err = gme.set_sample_rate(48000).unwrap_err();
+        assert_eq!(err.kind(), GmeErrorKind::Unsupported);
+        gme.set_sample_rate(44100).unwrap();
     }
 }